@@ -0,0 +1,122 @@
+//! A bot that plays the game by itself, e.g. for a demo mode. It drives the first player's snake.
+
+use crate::{Coord, FoodKind, Game, Input, Tile, WallMode, INPUTS};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Choose the next move of the first player's snake: the first step of a shortest path to the
+/// nearest food that avoids walls and snakes. If no food can be reached, move anywhere that doesn't
+/// kill the snake, preferring cells closer to the food. If there's nowhere like that either, keep
+/// going. Portals are avoided, as if they were walls.
+pub fn next_move(game: &Game) -> Input {
+    path_to_food(game)
+        .or_else(|| safe_move(game))
+        .unwrap_or(game.snakes[0].heading)
+}
+
+/// Whether the snake can move into `coord` without dying, and without eating shrink food it
+/// doesn't need to. The tail moves out of the way as the head moves in, but it's treated as blocked
+/// here to stay on the safe side.
+fn is_free(game: &Game, coord: &Coord) -> bool {
+    match game.tile_at(coord) {
+        Tile::AIR | Tile::FOOD | Tile::TRAIL => true,
+        Tile::SHRINK => target_kind(game) == FoodKind::Shrink,
+        _ => false,
+    }
+}
+
+/// The kind of food worth going for: normal food if there's any, and otherwise shrink food, since
+/// no new food is placed until some is eaten.
+fn target_kind(game: &Game) -> FoodKind {
+    if game.food.iter().any(|food| food.kind == FoodKind::Normal) {
+        FoodKind::Normal
+    } else {
+        FoodKind::Shrink
+    }
+}
+
+/// The moves from `coord` that end up on a free cell, along with that cell.
+fn free_moves<'a>(game: &'a Game, coord: &'a Coord) -> impl Iterator<Item = (Input, Coord)> + 'a {
+    INPUTS
+        .into_iter()
+        .map(move |input| (input, game.move_on_board(coord, &input)))
+        .filter(move |(_, next)| is_free(game, next))
+}
+
+/// The moves the first player's snake can make next tick without dying.
+fn safe_first_moves(game: &Game) -> impl Iterator<Item = (Input, Coord)> + '_ {
+    free_moves(game, game.head()).filter(|(input, _)| !game.is_reversal(0, input))
+}
+
+/// The number of moves between `a` and `b` on an empty board.
+fn distance(game: &Game, a: &Coord, b: &Coord) -> isize {
+    let (width, height) = game.coord_limits();
+    let (mut dx, mut dy) = ((a.x - b.x).abs(), (a.y - b.y).abs());
+    if game.wall_mode == WallMode::Wrap {
+        dx = dx.min(width - dx);
+        dy = dy.min(height - dy);
+    }
+    dx + dy
+}
+
+/// Whether there's food at `coord` that's worth going for.
+fn is_food(game: &Game, coord: &Coord) -> bool {
+    game.food_at(coord)
+        .is_some_and(|food| food.kind == target_kind(game))
+}
+
+fn distance_to_food(game: &Game, coord: &Coord) -> isize {
+    let target_kind = target_kind(game);
+    game.food
+        .iter()
+        .filter(|food| food.kind == target_kind)
+        .map(|food| distance(game, coord, &food.coord))
+        .min()
+        .unwrap_or(0)
+}
+
+/// The first move of a shortest path to the nearest food, found with A*, or `None` if no food can
+/// be reached.
+fn path_to_food(game: &Game) -> Option<Input> {
+    // the cost of the cheapest known path to each cell, and the first move of that path
+    let mut best_paths: HashMap<Coord, (isize, Input)> = HashMap::new();
+    let mut open = BinaryHeap::new();
+    for (input, coord) in safe_first_moves(game) {
+        open.push(Reverse((
+            1 + distance_to_food(game, &coord),
+            1,
+            coord.clone(),
+        )));
+        best_paths.insert(coord, (1, input));
+    }
+
+    while let Some(Reverse((_, cost, coord))) = open.pop() {
+        let (best_cost, first_move) = best_paths[&coord];
+        if cost > best_cost {
+            // a cheaper path to this cell was found after this one was queued
+            continue;
+        }
+        if is_food(game, &coord) {
+            return Some(first_move);
+        }
+        for (_, next) in free_moves(game, &coord) {
+            let next_cost = cost + 1;
+            if best_paths
+                .get(&next)
+                .is_none_or(|(known_cost, _)| next_cost < *known_cost)
+            {
+                let estimate = next_cost + distance_to_food(game, &next);
+                open.push(Reverse((estimate, next_cost, next.clone())));
+                best_paths.insert(next, (next_cost, first_move));
+            }
+        }
+    }
+    None
+}
+
+/// The move that doesn't kill the snake and gets closest to the food, if there's one.
+fn safe_move(game: &Game) -> Option<Input> {
+    safe_first_moves(game)
+        .min_by_key(|(_, coord)| distance_to_food(game, coord))
+        .map(|(input, _)| input)
+}
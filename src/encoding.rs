@@ -0,0 +1,385 @@
+//! A compact binary encoding of what's on the board, for sending games over the network and the
+//! like. See `Game::encode` for the layout.
+
+use crate::{Coord, DeathCause, Food, FoodKind, Game, GameError, GameState, Input, Snake};
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use std::collections::HashSet;
+
+/// The first byte of every encoding, to be bumped whenever the layout changes.
+const VERSION: u8 = 2;
+
+impl Game {
+    /// Encode the board, the snakes, the food and the state of the game into a few bytes. Settings
+    /// that aren't shown on the board, such as the theme or the wall mode, aren't included, and
+    /// neither is a recording.
+    ///
+    /// Every number is an unsigned LEB128 varint: seven bits per byte, least significant first,
+    /// with the top bit set on every byte but the last. Coordinates are their x and then their y,
+    /// and lists are their length and then their items. In order, the encoding is:
+    ///
+    /// - the version, currently 2, as a single byte
+    /// - the width and the height of the board
+    /// - the state: 0 for running, 1 for dead, 2 for won and 3 for quit
+    /// - the winner plus 1, or 0 if there isn't one
+    /// - the death cause: 0 for none, then 1 to 4 for own body, border, obstacle and other snake
+    /// - the number of ticks done
+    /// - the seed, then how many 32-bit words have been drawn from the random number generator
+    /// - the interior walls, as a list of coordinates
+    /// - the portals, as a list of pairs of coordinates
+    /// - the snakes, as a list of: the heading (0 to 3 for up, down, left and right), the score,
+    ///   and the body as a list of coordinates, head first
+    /// - the food, as a list of: the kind (0 for normal, 1 for shrink) and the coordinate
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![VERSION];
+        push_varint(&mut bytes, self.width.into());
+        push_varint(&mut bytes, self.height.into());
+        push_varint(&mut bytes, state_code(self.state));
+        push_varint(
+            &mut bytes,
+            self.winner.map_or(0, |winner| winner as u128 + 1),
+        );
+        push_varint(
+            &mut bytes,
+            match self.death_cause {
+                None => 0,
+                Some(DeathCause::OwnBody) => 1,
+                Some(DeathCause::Border) => 2,
+                Some(DeathCause::Obstacle) => 3,
+                Some(DeathCause::OtherSnake) => 4,
+            },
+        );
+        push_varint(&mut bytes, self.ticks.into());
+        push_varint(&mut bytes, self.seed.into());
+        push_varint(&mut bytes, self.rng.get_word_pos());
+
+        // sorted so that equal games encode the same, whatever order the set iterates in
+        let mut walls: Vec<&Coord> = self.walls.iter().collect();
+        walls.sort();
+        push_varint(&mut bytes, walls.len() as u128);
+        for wall in walls {
+            push_coord(&mut bytes, wall);
+        }
+
+        push_varint(&mut bytes, self.portals.len() as u128);
+        for (a, b) in &self.portals {
+            push_coord(&mut bytes, a);
+            push_coord(&mut bytes, b);
+        }
+
+        push_varint(&mut bytes, self.snakes.len() as u128);
+        for snake in &self.snakes {
+            push_varint(
+                &mut bytes,
+                match snake.heading {
+                    Input::UP => 0,
+                    Input::DOWN => 1,
+                    Input::LEFT => 2,
+                    Input::RIGHT => 3,
+                },
+            );
+            push_varint(&mut bytes, snake.food_eaten as u128);
+            push_varint(&mut bytes, snake.body.len() as u128);
+            for coord in &snake.body {
+                push_coord(&mut bytes, coord);
+            }
+        }
+
+        push_varint(&mut bytes, self.food.len() as u128);
+        for food in &self.food {
+            push_varint(&mut bytes, food_kind_code(food.kind));
+            push_coord(&mut bytes, &food.coord);
+        }
+        bytes
+    }
+
+    /// Decode a game encoded by `encode`. Whatever isn't part of the encoding is as in a game
+    /// created with `Game::builder`, and the snakes have no queued inputs. `Game::reset` goes back
+    /// to the game as decoded.
+    pub fn decode(bytes: &[u8]) -> Result<Game, GameError> {
+        let mut decoder = Decoder::new(bytes);
+        let version = decoder.varint()?;
+        if version != u128::from(VERSION) {
+            return Err(invalid(format!("unsupported version {}", version)));
+        }
+        let width = decoder.number()?;
+        let height = decoder.number()?;
+        let mut game = Game::builder(height, width).build()?;
+
+        game.state = decoder.state()?;
+        game.winner = match decoder.number::<usize>()? {
+            0 => None,
+            winner => Some(winner - 1),
+        };
+        game.death_cause = match decoder.varint()? {
+            0 => None,
+            1 => Some(DeathCause::OwnBody),
+            2 => Some(DeathCause::Border),
+            3 => Some(DeathCause::Obstacle),
+            4 => Some(DeathCause::OtherSnake),
+            cause => return Err(invalid(format!("invalid death cause {}", cause))),
+        };
+        game.ticks = decoder.number()?;
+        game.seed = decoder.number()?;
+        game.rng = ChaCha12Rng::seed_from_u64(game.seed);
+        game.rng.set_word_pos(decoder.varint()?);
+
+        let mut walls = HashSet::new();
+        for _ in 0..decoder.number::<usize>()? {
+            walls.insert(decoder.coord(&game)?);
+        }
+        game.walls = walls;
+
+        let mut portals = Vec::new();
+        for _ in 0..decoder.number::<usize>()? {
+            portals.push((decoder.coord(&game)?, decoder.coord(&game)?));
+        }
+        game.portals = portals;
+
+        let mut snakes = Vec::new();
+        for _ in 0..decoder.number::<usize>()? {
+            let heading = match decoder.varint()? {
+                0 => Input::UP,
+                1 => Input::DOWN,
+                2 => Input::LEFT,
+                3 => Input::RIGHT,
+                heading => return Err(invalid(format!("invalid heading {}", heading))),
+            };
+            let food_eaten = decoder.number()?;
+            let mut body = Vec::new();
+            for _ in 0..decoder.number::<usize>()? {
+                body.push(decoder.coord(&game)?);
+            }
+            if body.is_empty() {
+                return Err(invalid("a snake has no body".to_string()));
+            }
+            let mut snake = Snake::new(body, heading);
+            snake.food_eaten = food_eaten;
+            snakes.push(snake);
+        }
+        if snakes.is_empty() {
+            return Err(invalid("there are no snakes".to_string()));
+        }
+        if game.winner.is_some_and(|winner| winner >= snakes.len()) {
+            return Err(invalid("the winner isn't one of the players".to_string()));
+        }
+        game.snakes = snakes;
+
+        let mut food = Vec::new();
+        for _ in 0..decoder.number::<usize>()? {
+            let kind = decoder.food_kind()?;
+            food.push(Food {
+                coord: decoder.coord(&game)?,
+                kind,
+            });
+        }
+        game.food = food;
+
+        decoder.finish()?;
+        game.save_initial();
+        Ok(game)
+    }
+}
+
+pub(crate) fn invalid(reason: String) -> GameError {
+    GameError::InvalidEncoding(reason)
+}
+
+pub(crate) fn state_code(state: GameState) -> u128 {
+    match state {
+        GameState::RUNNING => 0,
+        GameState::DEAD => 1,
+        GameState::WON => 2,
+        GameState::QUIT => 3,
+    }
+}
+
+pub(crate) fn food_kind_code(kind: FoodKind) -> u128 {
+    match kind {
+        FoodKind::Normal => 0,
+        FoodKind::Shrink => 1,
+    }
+}
+
+pub(crate) fn push_varint(bytes: &mut Vec<u8>, mut value: u128) {
+    while value >= 0x80 {
+        bytes.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+pub(crate) fn push_coord(bytes: &mut Vec<u8>, coord: &Coord) {
+    // coordinates on the board are never negative
+    push_varint(bytes, coord.x as u128);
+    push_varint(bytes, coord.y as u128);
+}
+
+pub(crate) struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Decoder<'_> {
+    pub(crate) fn new(bytes: &[u8]) -> Decoder<'_> {
+        Decoder { bytes, position: 0 }
+    }
+
+    /// Check that every byte has been decoded.
+    pub(crate) fn finish(&self) -> Result<(), GameError> {
+        if self.position != self.bytes.len() {
+            return Err(invalid("there are bytes left over".to_string()));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn varint(&mut self) -> Result<u128, GameError> {
+        let mut value = 0;
+        for shift in (0..u128::BITS).step_by(7) {
+            let byte = *self
+                .bytes
+                .get(self.position)
+                .ok_or_else(|| invalid("it ends early".to_string()))?;
+            self.position += 1;
+            value |= u128::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid(format!(
+            "the number at byte {} is too long",
+            self.position
+        )))
+    }
+
+    /// A varint that must fit in `T`.
+    pub(crate) fn number<T: TryFrom<u128>>(&mut self) -> Result<T, GameError> {
+        let value = self.varint()?;
+        T::try_from(value).map_err(|_| invalid(format!("{} is out of range", value)))
+    }
+
+    pub(crate) fn state(&mut self) -> Result<GameState, GameError> {
+        match self.varint()? {
+            0 => Ok(GameState::RUNNING),
+            1 => Ok(GameState::DEAD),
+            2 => Ok(GameState::WON),
+            3 => Ok(GameState::QUIT),
+            state => Err(invalid(format!("invalid state {}", state))),
+        }
+    }
+
+    pub(crate) fn food_kind(&mut self) -> Result<FoodKind, GameError> {
+        match self.varint()? {
+            0 => Ok(FoodKind::Normal),
+            1 => Ok(FoodKind::Shrink),
+            kind => Err(invalid(format!("invalid food kind {}", kind))),
+        }
+    }
+
+    /// A coordinate, which may be anywhere with non-negative x and y.
+    pub(crate) fn any_coord(&mut self) -> Result<Coord, GameError> {
+        Ok(Coord {
+            x: self.number()?,
+            y: self.number()?,
+        })
+    }
+
+    /// A coordinate that must be on the board of `game`.
+    fn coord(&mut self, game: &Game) -> Result<Coord, GameError> {
+        let coord = self.any_coord()?;
+        if !game.coord_is_in_bounds(&coord) {
+            return Err(invalid(format!("{} is out of bounds", coord)));
+        }
+        Ok(coord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The encoding of a 3 by 3 running game with no ticks done, up to just before the walls.
+    fn header() -> Vec<u8> {
+        let mut bytes = vec![VERSION];
+        for value in [3, 3, 0, 0, 0, 0, 0, 0] {
+            push_varint(&mut bytes, value);
+        }
+        bytes
+    }
+
+    fn push_all(bytes: &mut Vec<u8>, values: &[u128]) {
+        for &value in values {
+            push_varint(bytes, value);
+        }
+    }
+
+    #[test]
+    fn decoded_games_play_on_the_same() {
+        let mut game = Game::builder(8, 12).seed(5).build().unwrap();
+        for _ in 0..10 {
+            game.step(crate::ai::next_move(&game));
+        }
+        let mut decoded = Game::decode(&game.encode()).unwrap();
+        assert_eq!(decoded.encode(), game.encode());
+        for _ in 0..30 {
+            let input = crate::ai::next_move(&game);
+            assert_eq!(game.step(input), decoded.step(input));
+            assert_eq!(decoded.encode(), game.encode());
+        }
+    }
+
+    #[test]
+    fn unsupported_versions_are_rejected() {
+        let mut bytes = Game::create(3, 3).unwrap().encode();
+        bytes[0] = VERSION + 1;
+        assert_eq!(
+            Game::decode(&bytes).err(),
+            Some(invalid(format!("unsupported version {}", VERSION + 1)))
+        );
+    }
+
+    #[test]
+    fn coordinates_off_the_board_are_rejected() {
+        let mut bytes = header();
+        // a wall at (3, 0)
+        push_all(&mut bytes, &[1, 3, 0]);
+        assert_eq!(
+            Game::decode(&bytes).err(),
+            Some(invalid("(3, 0) is out of bounds".to_string()))
+        );
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected() {
+        let mut bytes = Game::create(3, 3).unwrap().encode();
+        bytes.push(0);
+        assert_eq!(
+            Game::decode(&bytes).err(),
+            Some(invalid("there are bytes left over".to_string()))
+        );
+    }
+
+    #[test]
+    fn snakes_without_a_body_are_rejected() {
+        let mut bytes = header();
+        // no walls or portals, and one snake heading up with a score of 0 and no segments
+        push_all(&mut bytes, &[0, 0, 1, 0, 0, 0]);
+        assert_eq!(
+            Game::decode(&bytes).err(),
+            Some(invalid("a snake has no body".to_string()))
+        );
+    }
+
+    #[test]
+    fn winners_that_arent_players_are_rejected() {
+        let mut bytes = vec![VERSION];
+        // won by the second player of a game with only one
+        push_all(&mut bytes, &[3, 3, 2, 2, 0, 0, 0, 0]);
+        // no walls or portals, one snake heading up with a score of 0 at (0, 0), and no food
+        push_all(&mut bytes, &[0, 0, 1, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(
+            Game::decode(&bytes).err(),
+            Some(invalid("the winner isn't one of the players".to_string()))
+        );
+    }
+}
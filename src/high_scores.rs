@@ -0,0 +1,100 @@
+//! A persistent list of the best scores, stored as a plain-text file with one `<score> <name>` line
+//! per entry, best first.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How many scores are kept in the file.
+const MAX_HIGH_SCORES: usize = 10;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct HighScore {
+    pub name: String,
+    pub score: usize,
+}
+
+/// Where the high scores are kept by default: `snake/high_scores.txt` in the user's config
+/// directory, or `None` if that can't be determined.
+pub fn default_path() -> Option<PathBuf> {
+    let config_dir = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    };
+    config_dir.map(|dir| dir.join("snake").join("high_scores.txt"))
+}
+
+/// Load the high scores at `path`, best first. A missing or corrupt file is treated as having no
+/// scores at all.
+pub fn load_high_scores(path: &Path) -> Vec<HighScore> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut high_scores = Vec::new();
+    for line in contents.lines() {
+        let parsed = line
+            .split_once(' ')
+            .and_then(|(score, name)| Some((score.parse().ok()?, name)));
+        match parsed {
+            Some((score, name)) => high_scores.push(HighScore {
+                name: name.to_string(),
+                score,
+            }),
+            None => return Vec::new(),
+        }
+    }
+    high_scores.sort_by_key(|high_score| std::cmp::Reverse(high_score.score));
+    high_scores
+}
+
+/// Add a score to the high scores at `path`, creating the file if needed. Returns whether it
+/// beats the previous best, or is the first one, which a score of 0 never does.
+pub fn record_score(path: &Path, name: &str, score: usize) -> io::Result<bool> {
+    let mut high_scores = load_high_scores(path);
+    let is_best = score > 0 && high_scores.first().is_none_or(|best| score > best.score);
+
+    // a name with a line break in it would corrupt the file
+    let name = name.replace(['\r', '\n'], " ");
+    let index = high_scores.partition_point(|high_score| high_score.score >= score);
+    high_scores.insert(index, HighScore { name, score });
+    high_scores.truncate(MAX_HIGH_SCORES);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents: String = high_scores
+        .iter()
+        .map(|high_score| format!("{} {}\n", high_score.score, high_score.name))
+        .collect();
+    fs::write(path, contents)?;
+    Ok(is_best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_scores_above_0_are_new_bests() {
+        let path = env::temp_dir()
+            .join(format!("snake-high-scores-{}", std::process::id()))
+            .join("high_scores.txt");
+        let _ = fs::remove_file(&path);
+        assert!(!record_score(&path, "a", 0).unwrap());
+        assert!(record_score(&path, "b", 3).unwrap());
+        assert!(!record_score(&path, "c", 3).unwrap());
+        assert!(record_score(&path, "d", 4).unwrap());
+        let scores: Vec<usize> = load_high_scores(&path)
+            .iter()
+            .map(|high_score| high_score.score)
+            .collect();
+        assert_eq!(scores, [4, 3, 3, 0]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+//! Loading a board from a text layout, for custom levels. See `Game::from_layout` for the format.
+
+use crate::{Coord, Food, FoodKind, Game, GameError, Input, INPUTS};
+use std::collections::HashSet;
+
+impl Game {
+    /// Create a game from a layout of the board, border included, with one line per row and one
+    /// character per cell:
+    ///
+    /// - `#` is a wall. The border must be all walls, and any inside of it are interior walls.
+    /// - `o` is a segment of the snake, and `@` its head. The segments must form a single line,
+    ///   each next to the one before it. Without `@`, the head is the first `o` in row-major order,
+    ///   so it must be at one of the ends.
+    /// - `*` is food and `-` shrink food. Without either, food is placed at random as usual.
+    /// - a space is empty.
+    ///
+    /// The snake starts moving away from its second segment, or down if it has only one. As many
+    /// food items are kept on the board as the layout has.
+    pub fn from_layout(layout: &str) -> Result<Game, GameError> {
+        let invalid = |reason: String| GameError::InvalidLayout(reason);
+        let rows: Vec<Vec<char>> = layout.lines().map(|line| line.chars().collect()).collect();
+        let columns = rows.first().map_or(0, |row| row.len());
+        if let Some(y) = rows.iter().position(|row| row.len() != columns) {
+            return Err(invalid(format!(
+                "row {} is {} characters long instead of {}",
+                y,
+                rows[y].len(),
+                columns
+            )));
+        }
+        let height = u16::try_from(rows.len().saturating_sub(2))
+            .map_err(|_| invalid("it's too tall".to_string()))?;
+        let width = u16::try_from(columns.saturating_sub(2))
+            .map_err(|_| invalid("it's too wide".to_string()))?;
+
+        let mut walls = HashSet::new();
+        let mut head = None;
+        let mut segments = Vec::new();
+        let mut food = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &c) in row.iter().enumerate() {
+                let is_border = y == 0 || y == rows.len() - 1 || x == 0 || x == columns - 1;
+                // board coordinates start inside of the border
+                let coord = Coord {
+                    x: x as isize - 1,
+                    y: y as isize - 1,
+                };
+                match c {
+                    '#' if is_border => {}
+                    _ if is_border => {
+                        return Err(invalid(format!("the border at {} isn't a wall", coord)));
+                    }
+                    '#' => {
+                        walls.insert(coord);
+                    }
+                    'o' => segments.push(coord),
+                    '@' if head.is_some() => {
+                        return Err(invalid("there's more than one head".to_string()));
+                    }
+                    '@' => head = Some(coord),
+                    '*' => food.push(Food {
+                        coord,
+                        kind: FoodKind::Normal,
+                    }),
+                    '-' => food.push(Food {
+                        coord,
+                        kind: FoodKind::Shrink,
+                    }),
+                    ' ' => {}
+                    c => return Err(invalid(format!("unknown character {:?} at {}", c, coord))),
+                }
+            }
+        }
+
+        let head = match head {
+            Some(head) => head,
+            None if segments.is_empty() => return Err(invalid("there's no snake".to_string())),
+            None => segments.remove(0),
+        };
+        let body = trace_snake(head, segments).map_err(invalid)?;
+        let heading = match body.get(1) {
+            Some(neck) => *INPUTS
+                .iter()
+                .find(|input| neck.move_by(input) == body[0])
+                .unwrap(),
+            None => Input::DOWN,
+        };
+
+        let mut builder = Game::builder(height, width)
+            .walls(walls)
+            .start(body, heading);
+        if !food.is_empty() {
+            builder = builder.food_count(food.len());
+        }
+        let mut game = builder.build()?;
+        if !food.is_empty() {
+            game.food = food;
+            game.save_initial();
+        }
+        Ok(game)
+    }
+}
+
+/// Order `segments` into a snake starting at `head`, each next to the one before it. Fails if
+/// that can't be done unambiguously with all of them.
+fn trace_snake(head: Coord, mut segments: Vec<Coord>) -> Result<Vec<Coord>, String> {
+    let mut body = vec![head];
+    while !segments.is_empty() {
+        let last = body.last().unwrap();
+        let next: Vec<usize> = (0..segments.len())
+            .filter(|&index| last.neighbors().contains(&segments[index]))
+            .collect();
+        match next[..] {
+            [index] => body.push(segments.remove(index)),
+            [] => {
+                return Err(format!(
+                    "the snake ends at {}, but there are more segments",
+                    last
+                ))
+            }
+            _ => return Err(format!("the snake branches at {}", last)),
+        }
+    }
+    Ok(body)
+}
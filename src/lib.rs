@@ -1,48 +1,128 @@
-use core::time;
-use crossterm::{
-    cursor::{self, Hide},
-    event::{
-        poll, read, Event,
-        KeyCode::{Char, Down, Left, Right, Up},
-        KeyEvent,
-    },
-    style::{Print, Stylize},
-    terminal::{Clear, ClearType},
-    QueueableCommand,
+pub mod ai;
+pub mod encoding;
+pub mod high_scores;
+pub mod layout;
+#[cfg(feature = "spectate")]
+pub mod spectate;
+#[cfg(feature = "terminal")]
+mod terminal;
+#[cfg(feature = "terminal")]
+mod theme;
+
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::{fmt, ops::Add, str::FromStr, time::SystemTime};
+#[cfg(feature = "terminal")]
+pub use terminal::{check_fits_terminal, GameEvent, InteractiveGame};
+#[cfg(feature = "terminal")]
+pub use theme::{
+    set_color_choice, supports_truecolor, BorderStyle, ColorChoice, KeyBindings, Orientation,
+    Theme, TileStyle,
 };
-use rand::{thread_rng, Rng};
-use std::io::{stdout, Write};
-use std::sync::{Arc, Mutex};
-use std::thread;
-use std::{collections::HashSet, io::Stdout};
-use std::{fmt, ops::Add};
 
 const WALL_STR: &str = "█";
 const SNAKE_STR: &str = "●";
 const FOOD_STR: &str = "*";
+const SHRINK_FOOD_STR: &str = "-";
+const TRAIL_STR: &str = "·";
 const AIR_STR: &str = " ";
+const PORTAL_STR: &str = "O";
 
-#[derive(Clone)]
+/// How many inputs `Game::queue_input` holds on to before dropping new ones.
+const INPUT_QUEUE_CAPACITY: usize = 3;
+
+/// How many segments eating shrink food takes off the snake, as far as it's long enough.
+const SHRINK_FOOD_SEGMENTS: usize = 3;
+
+/// The chance each tick that skittish food near the head of a snake runs away.
+const SKITTISH_FOOD_CHANCE: f64 = 0.25;
+
+/// Every direction, for when they all need to be tried.
+const INPUTS: [Input; 4] = [Input::UP, Input::DOWN, Input::LEFT, Input::RIGHT];
+
+/// How often `bench_run` moves towards the food rather than at random.
+const BENCH_RUN_GREED: f64 = 0.8;
+
+/// How many random cells `Game::random_free_coord` tries before enumerating the free cells.
+const RANDOM_FREE_COORD_TRIES: usize = 8;
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Tile {
+    /// The head of the first player's snake.
+    HEAD,
     SNAKE,
+    /// The head of the snake of any player but the first.
+    RIVALHEAD,
+    /// The snake of any player but the first.
+    RIVAL,
     FOOD,
+    /// Food that shrinks the snake instead of growing it.
+    SHRINK,
+    /// A cell a tail left recently, while it fades out. Only drawn when the game has a trail.
+    TRAIL,
     AIR,
     WALL,
+    /// One end of a pair of portals, when there's no snake on it.
+    PORTAL,
+}
+
+impl Tile {
+    /// The tile of food of the given kind.
+    pub fn for_food(kind: FoodKind) -> Tile {
+        match kind {
+            FoodKind::Normal => Tile::FOOD,
+            FoodKind::Shrink => Tile::SHRINK,
+        }
+    }
+
+    /// The tile of a segment of `player`'s snake, other than the head.
+    pub fn for_player(player: usize) -> Tile {
+        if player == 0 {
+            Tile::SNAKE
+        } else {
+            Tile::RIVAL
+        }
+    }
+
+    /// The tile of the head of `player`'s snake.
+    pub fn head_for_player(player: usize) -> Tile {
+        if player == 0 {
+            Tile::HEAD
+        } else {
+            Tile::RIVALHEAD
+        }
+    }
+
+    /// The glyph the default theme draws this tile with.
+    #[cfg(not(feature = "terminal"))]
+    fn default_glyph(&self) -> &'static str {
+        match self {
+            Tile::HEAD | Tile::SNAKE | Tile::RIVALHEAD | Tile::RIVAL => SNAKE_STR,
+            Tile::FOOD => FOOD_STR,
+            Tile::SHRINK => SHRINK_FOOD_STR,
+            Tile::TRAIL => TRAIL_STR,
+            Tile::AIR => AIR_STR,
+            Tile::WALL => WALL_STR,
+            Tile::PORTAL => PORTAL_STR,
+        }
+    }
 }
 
+#[cfg(not(feature = "terminal"))]
 impl fmt::Display for Tile {
+    /// The tile as drawn by the default theme, which without the terminal feature is plain text.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let tile_str = match self {
-            Tile::SNAKE => SNAKE_STR.green(),
-            Tile::FOOD => FOOD_STR.red(),
-            Tile::AIR => AIR_STR.stylize(),
-            Tile::WALL => WALL_STR.white(),
-        };
-        write!(f, "{}", tile_str)
+        f.write_str(self.default_glyph())
     }
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Input {
     UP,
     DOWN,
@@ -51,13 +131,29 @@ pub enum Input {
 }
 
 impl Input {
-    pub fn from_key(s: &str) -> Option<Input> {
-        match s {
-            "w" => Some(Input::UP),
-            "a" => Some(Input::LEFT),
-            "s" => Some(Input::DOWN),
-            "d" => Some(Input::RIGHT),
-            _ => None,
+    /// The input that `key_bindings` bind the key `s` to, for either player, where `s` is a single
+    /// character or the name of an arrow key, like `up`. Pass `&KeyBindings::default()` for the
+    /// default controls.
+    #[cfg(feature = "terminal")]
+    pub fn from_key(s: &str, key_bindings: &KeyBindings) -> Option<Input> {
+        key_bindings.input(theme::parse_key_code(s)?)
+    }
+
+    /// The input whose `key` is `s`, in either case.
+    fn from_replay_key(s: &str) -> Option<Input> {
+        INPUTS
+            .into_iter()
+            .find(|input| input.key().eq_ignore_ascii_case(s))
+    }
+
+    /// The key that stands for this input in a `Replay`, which is the same whatever the controls
+    /// were when it was recorded.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Input::UP => "w",
+            Input::LEFT => "a",
+            Input::DOWN => "s",
+            Input::RIGHT => "d",
         }
     }
 
@@ -80,7 +176,9 @@ impl Input {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+/// A cell on the board. Coordinates are ordered by `x` and then by `y`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Coord {
     x: isize, // these must be larger than the types of the height/width of the board and must be signed
     y: isize,
@@ -98,6 +196,30 @@ impl Add for Coord {
 }
 
 impl Coord {
+    pub fn new(x: isize, y: isize) -> Coord {
+        Coord { x, y }
+    }
+
+    pub fn x(&self) -> isize {
+        self.x
+    }
+
+    pub fn y(&self) -> isize {
+        self.y
+    }
+
+    /// The number of moves between this coordinate and `other` along the axes, not counting any
+    /// walls in the way or wrapping around the board.
+    pub fn manhattan_distance(&self, other: &Coord) -> usize {
+        (self.x - other.x).unsigned_abs() + (self.y - other.y).unsigned_abs()
+    }
+
+    /// The four coordinates next to this one, in the order up, down, left, right. They may be off
+    /// of the board.
+    pub fn neighbors(&self) -> [Coord; 4] {
+        INPUTS.map(|input| self.move_by(&input))
+    }
+
     fn move_by(&self, input: &Input) -> Coord {
         let offset = input.offset();
         self.clone() + offset
@@ -113,8 +235,17 @@ impl fmt::Display for Coord {
 
 pub enum TermUpdateType {
     Clear,
-    Snake,
-    Food,
+    /// A segment of the snake of the given player, other than the head.
+    Snake(usize),
+    /// The head of the snake of the given player.
+    SnakeHead(usize),
+    Food(FoodKind),
+    /// A cell a tail left recently.
+    Trail,
+    /// A wall raised during the game, where food was eaten in `Mode::Hard`.
+    Wall,
+    /// A portal a snake has moved off of.
+    Portal,
 }
 
 pub struct TermUpdate {
@@ -123,292 +254,2474 @@ pub struct TermUpdate {
 }
 
 impl TermUpdate {
-    pub fn queue(&self, stdout: &mut Stdout) -> crossterm::Result<()> {
-        let tile = match self.type_ {
+    /// The board coordinate of the cell that this update draws.
+    pub fn coord(&self) -> &Coord {
+        &self.coord
+    }
+
+    /// The tile that this update draws.
+    pub fn tile(&self) -> Tile {
+        match self.type_ {
             TermUpdateType::Clear => Tile::AIR,
-            TermUpdateType::Snake => Tile::SNAKE,
-            TermUpdateType::Food => Tile::FOOD,
-        };
-        // offset by (+1, +1) for walls
-        stdout
-            .queue(cursor::MoveTo(
-                u16::try_from(self.coord.x).unwrap() + 1,
-                u16::try_from(self.coord.y).unwrap() + 1,
-            ))?
-            .queue(Print(tile))?;
-        Ok(())
+            TermUpdateType::Snake(player) => Tile::for_player(player),
+            TermUpdateType::SnakeHead(player) => Tile::head_for_player(player),
+            TermUpdateType::Food(kind) => Tile::for_food(kind),
+            TermUpdateType::Trail => Tile::TRAIL,
+            TermUpdateType::Wall => Tile::WALL,
+            TermUpdateType::Portal => Tile::PORTAL,
+        }
     }
 }
 
-#[derive(PartialEq, Debug)]
+/// What eating a food item does.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FoodKind {
+    /// Grows the snake by a segment and scores a point.
+    Normal,
+    /// Takes a few segments off the snake's tail, but never its last one. It doesn't score.
+    Shrink,
+}
+
+/// Whether food stays where it's placed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FoodBehavior {
+    /// Food stays put until it's eaten.
+    Static,
+    /// Food that's at most `radius` cells away from the head of a snake, counting steps along
+    /// either axis, sometimes runs away to a random free cell, so it has to be chased.
+    Skittish { radius: usize },
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Food {
+    coord: Coord,
+    kind: FoodKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum GameState {
     RUNNING,
     DEAD,
     WON,
+    /// The player ended the game early.
+    QUIT,
+}
+
+/// How a game ended up, as returned by `InteractiveGame::run` and `Game::run_script`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GameSummary {
+    pub state: GameState,
+    /// The first player's score.
+    pub score: usize,
+    pub ticks: u64,
+}
+
+/// Play a seeded game for up to `steps` ticks with no terminal at all, as a stable workload for
+/// benchmarking `Game::tick` and food placement. The snake mostly heads for the nearest food, so it
+/// grows long and food is placed often, and otherwise takes a random move that doesn't kill it.
+/// Deaths respawn the snake like `Game::set_endless_respawn` does, so that the run doesn't end as
+/// soon as the snake traps itself. The same arguments always play out the same. It stops early if
+/// the game ends anyway, e.g. because the board is full, which the summary's ticks tell. Boards
+/// smaller than 2 by 2 are made that big.
+pub fn bench_run(seed: u64, steps: usize, height: u16, width: u16) -> GameSummary {
+    let mut game = Game::create_seeded(height.max(2), width.max(2), seed)
+        .expect("the board is at least 2 by 2");
+    game.set_endless_respawn(true);
+    let mut rng = ChaCha12Rng::seed_from_u64(seed);
+    for _ in 0..steps {
+        if game.state != GameState::RUNNING {
+            break;
+        }
+        let moves = game.legal_moves();
+        let nearest_food = |input: &Input| {
+            let next = game.move_on_board(game.head(), input);
+            game.foods()
+                .map(|food| food.manhattan_distance(&next))
+                .min()
+        };
+        let input = if moves.is_empty() {
+            *game.heading()
+        } else if rng.gen_bool(BENCH_RUN_GREED) {
+            *moves
+                .iter()
+                .min_by_key(|input| nearest_food(input))
+                .unwrap()
+        } else {
+            moves[rng.gen_range(0..moves.len())]
+        };
+        game.step(input);
+    }
+    GameSummary {
+        state: game.state,
+        score: game.score(),
+        ticks: game.ticks,
+    }
+}
+
+/// The seed of the daily challenge on the given day, so that everyone who plays that day gets the
+/// same food given the same moves. It's the date written as YYYYMMDD, read as a number, e.g.
+/// 20240131 for the 31st of January 2024, and will stay that. The date isn't checked.
+pub fn daily_seed(year: u16, month: u8, day: u8) -> u64 {
+    u64::from(year) * 10000 + u64::from(month) * 100 + u64::from(day)
+}
+
+/// The `daily_seed` of today in UTC, so that it's the same day for everyone wherever they are.
+pub fn todays_seed() -> u64 {
+    let days = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() / (24 * 60 * 60));
+    let (year, month, day) = civil_date(days);
+    daily_seed(year, month, day)
+}
+
+/// The date in the Gregorian calendar `days` days after the 1st of January 1970, as its year,
+/// month and day. See http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+fn civil_date(days: u64) -> (u16, u8, u8) {
+    // counted from the 1st of March of the year 0, so that leap days are at the end of the year
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // from March
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (year as u16, month as u8, day as u8)
+}
+
+#[derive(PartialEq, Debug)]
+pub enum GameError {
+    BoardTooSmall {
+        height: u16,
+        width: u16,
+    },
+    /// The board and its border don't fit in the terminal, which is `columns` by `rows` characters.
+    BoardTooLargeForTerminal {
+        height: u16,
+        width: u16,
+        columns: u16,
+        rows: u16,
+    },
+    /// An interior wall is out of bounds or where the snake starts.
+    InvalidWall(Coord),
+    /// A replay couldn't be parsed. Holds the offending line.
+    InvalidReplay(String),
+    /// A snake's starting body or direction isn't possible. Holds what's wrong with it.
+    InvalidStart(String),
+    /// Bytes passed to `Game::decode` aren't a valid encoding. Holds what's wrong with them.
+    InvalidEncoding(String),
+    /// A layout passed to `Game::from_layout` can't be loaded. Holds what's wrong with it.
+    InvalidLayout(String),
+    /// Food can't be put at a cell passed to `Game::set_food`, which is out of bounds or taken.
+    InvalidFood(Coord),
+    /// One end of a portal is out of bounds, or on a wall, the starting snake or another portal.
+    InvalidPortal(Coord),
+    /// A game can't be recorded, as its replay wouldn't play out the same. Holds what it has that
+    /// a replay doesn't.
+    NotReplayable(String),
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::BoardTooSmall { height, width } => write!(
+                f,
+                "Board too small ({}x{}). Must have minimum dimension of 2.",
+                height, width
+            ),
+            GameError::BoardTooLargeForTerminal {
+                height,
+                width,
+                columns,
+                rows,
+            } => write!(
+                f,
+                "Board too large ({}x{}) for the terminal ({} columns, {} rows).",
+                height, width, columns, rows
+            ),
+            GameError::InvalidWall(coord) => write!(
+                f,
+                "Wall at {} is out of bounds or where the snake starts.",
+                coord
+            ),
+            GameError::InvalidReplay(line) => write!(f, "Invalid replay line: {:?}", line),
+            GameError::InvalidStart(reason) => write!(f, "Invalid starting snake: {}.", reason),
+            GameError::InvalidEncoding(reason) => write!(f, "Invalid encoded game: {}.", reason),
+            GameError::InvalidLayout(reason) => write!(f, "Invalid layout: {}.", reason),
+            GameError::InvalidFood(coord) => write!(
+                f,
+                "Food at {} is out of bounds or on a snake, a wall, other food or a portal.",
+                coord
+            ),
+            GameError::InvalidPortal(coord) => write!(
+                f,
+                "Portal at {} is out of bounds or on a wall, the snake or another portal.",
+                coord
+            ),
+            GameError::NotReplayable(reason) => write!(
+                f,
+                "Can't record a replay of a game with {}, as replays only have the board size and \
+                 the seed.",
+                reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+/// What happens when the snake moves past the edge of the board.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WallMode {
+    /// The border is a wall and running into it kills the snake.
+    Solid,
+    /// The board is a torus: moving off one edge reappears on the opposite edge.
+    Wrap,
+}
+
+/// The rules for what happens when a snake runs into something.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Mode {
+    /// Running into anything is deadly, as set up by the rest of the settings.
+    Classic,
+    /// Nothing is deadly: a snake passes through its own body, and a move into a wall or another
+    /// snake is rejected, so the snake stays put for that tick. The game only ends when it's won or
+    /// quit.
+    Endless,
+    /// Like `Classic`, but every food that's eaten leaves a wall where it was once the snake has
+    /// moved off of it, so the board gets more and more cramped.
+    Hard,
+}
+
+/// What wins the game, besides being the last snake alive in a multiplayer game.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WinCondition {
+    /// Fill the board, so that there's no room left for food.
+    FillBoard,
+    /// Grow a snake to at least this many segments.
+    Length(usize),
+    /// Stay alive for this many ticks.
+    Ticks(u64),
+}
+
+/// What a snake died of.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DeathCause {
+    /// It ran into its own body.
+    OwnBody,
+    /// It ran into the border of the board.
+    Border,
+    /// It ran into an interior wall.
+    Obstacle,
+    /// It ran into another player's snake.
+    OtherSnake,
+}
+
+impl fmt::Display for DeathCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeathCause::OwnBody => write!(f, "You hit yourself"),
+            DeathCause::Border => write!(f, "You hit the edge"),
+            DeathCause::Obstacle => write!(f, "You hit a wall"),
+            DeathCause::OtherSnake => write!(f, "You hit the other snake"),
+        }
+    }
+}
+
+/// What happens when a snake runs into itself.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SelfCollision {
+    /// Running into its own body kills the snake.
+    Deadly,
+    /// The snake passes through its own body harmlessly. Walls and other snakes are still deadly.
+    Passable,
+    /// The snake bites itself off where it runs into its own body: that segment and everything
+    /// behind it are lost, and the snake carries on shorter. Walls and other snakes are still
+    /// deadly.
+    Bite,
+}
+
+/// One player's snake.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Snake {
+    body: Vec<Coord>, // head first
+    cur_input: Input,
+    heading: Input, // the direction of the last move
+    input_queue: VecDeque<Input>,
+    food_eaten: usize,
+    pending_growth: usize,  // how many more ticks the tail stays put for
+    last_turn: Option<u64>, // the tick a queued input last turned the snake on
+}
+
+impl Snake {
+    fn new(body: Vec<Coord>, heading: Input) -> Snake {
+        Snake {
+            body,
+            cur_input: heading,
+            heading,
+            input_queue: VecDeque::new(),
+            food_eaten: 0,
+            pending_growth: 0,
+            last_turn: None,
+        }
+    }
+
+    fn head(&self) -> &Coord {
+        &self.body[0]
+    }
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Game {
-    snake: Vec<Coord>,
-    food: Option<Coord>, // food may not be present if board is completely filled with snake
-    width: u8,
-    height: u8,
+    snakes: Vec<Snake>, // one per player, the first player's first
+    food: Vec<Food>, // there may be less than food_count if the board is nearly filled with snake
+    food_count: usize,
+    shrink_food_chance: f64,
+    food_behavior: FoodBehavior,
+    safe_start: bool,
+    grow_per_food: usize,
+    trail_length: usize,
+    trail: Vec<(Coord, usize)>, // cells tails left recently, with how many ticks ago they did
+    pending_walls: Vec<Coord>,  // cells food was eaten on in hard mode, until a snake leaves them
+    walls: HashSet<Coord>,      // interior walls only, the border isn't included
+    portals: Vec<(Coord, Coord)>,
+    shrink_every: u64,
+    turn_cooldown: u32,
+    width: u16,
+    height: u16,
     pub state: GameState,
-    pub cur_input: Input,
+    winner: Option<usize>,
+    death_cause: Option<DeathCause>,
+    endless_respawn: bool,
+    mode: Mode,
+    win_condition: WinCondition,
+    wall_mode: WallMode,
+    self_collision: SelfCollision,
+    #[cfg(feature = "terminal")]
+    theme: Theme,
+    ticks: u64,
+    recording: Option<Replay>,
+    // the game before each of the last ticks, oldest first, for undo
+    #[cfg_attr(feature = "serde", serde(skip))]
+    history: VecDeque<Game>,
+    history_depth: usize,
+    // the game as it was created, for reset, shared with the copies in the history
+    #[cfg_attr(feature = "serde", serde(skip))]
+    initial: Option<Arc<Game>>,
+    seed: u64,
+    // ChaCha12 is what StdRng currently is, but unlike StdRng it's guaranteed not to change between
+    // versions of rand, which would break seeded games and replays. It can also be serialized.
+    rng: ChaCha12Rng,
 }
 
-impl Game {
-    pub fn create(height: u8, width: u8) -> Game {
+/// Configures and creates a `Game`. Anything not set keeps the same defaults as `Game::create`.
+pub struct GameBuilder {
+    height: u16,
+    width: u16,
+    seed: Option<u64>,
+    wall_mode: WallMode,
+    self_collision: SelfCollision,
+    mode: Mode,
+    win_condition: WinCondition,
+    food_count: usize,
+    shrink_food_chance: f64,
+    food_behavior: FoodBehavior,
+    safe_start: bool,
+    grow_per_food: usize,
+    trail_length: usize,
+    walls: HashSet<Coord>,
+    portals: Vec<(Coord, Coord)>,
+    shrink_every: u64,
+    turn_cooldown: u32,
+    #[cfg(feature = "terminal")]
+    theme: Theme,
+    two_player: bool,
+    start: Option<(Vec<Coord>, Input)>,
+    history_depth: usize,
+}
+
+impl GameBuilder {
+    pub fn new(height: u16, width: u16) -> GameBuilder {
+        GameBuilder {
+            height,
+            width,
+            seed: None,
+            wall_mode: WallMode::Solid,
+            self_collision: SelfCollision::Deadly,
+            mode: Mode::Classic,
+            win_condition: WinCondition::FillBoard,
+            food_count: 1,
+            shrink_food_chance: 0.0,
+            food_behavior: FoodBehavior::Static,
+            safe_start: false,
+            grow_per_food: 1,
+            trail_length: 0,
+            walls: HashSet::new(),
+            portals: Vec::new(),
+            shrink_every: 0,
+            turn_cooldown: 0,
+            #[cfg(feature = "terminal")]
+            theme: Theme::default(),
+            two_player: false,
+            start: None,
+            history_depth: 0,
+        }
+    }
+
+    /// Seed the food placement, so that two games with the same seed and the same inputs play out
+    /// identically.
+    pub fn seed(mut self, seed: u64) -> GameBuilder {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn wall_mode(mut self, wall_mode: WallMode) -> GameBuilder {
+        self.wall_mode = wall_mode;
+        self
+    }
+
+    pub fn self_collision(mut self, self_collision: SelfCollision) -> GameBuilder {
+        self.self_collision = self_collision;
+        self
+    }
+
+    /// The default is `Mode::Classic`.
+    pub fn mode(mut self, mode: Mode) -> GameBuilder {
+        self.mode = mode;
+        self
+    }
+
+    /// What wins the game. The default is `WinCondition::FillBoard`. Filling the board wins with
+    /// any condition though, as there's no food left to play for.
+    pub fn win_condition(mut self, win_condition: WinCondition) -> GameBuilder {
+        self.win_condition = win_condition;
+        self
+    }
+
+    /// How many food items are on the board at once. There's always at least one.
+    pub fn food_count(mut self, food_count: usize) -> GameBuilder {
+        self.food_count = food_count.max(1);
+        self
+    }
+
+    /// The chance, from 0 to 1, that a newly placed food item is shrink food. The default is 0.
+    pub fn shrink_food_chance(mut self, chance: f64) -> GameBuilder {
+        self.shrink_food_chance = chance.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Keep the snakes from running off of the board on the very first tick: a snake headed off of
+    /// it turns to the first direction that stays on it instead, e.g. on a tiny board where there's
+    /// no time to react. The trade-off is that a first move that would leave the board is
+    /// overridden rather than honored, and only the first tick is covered. The default is `false`.
+    pub fn safe_start(mut self, safe_start: bool) -> GameBuilder {
+        self.safe_start = safe_start;
+        self
+    }
+
+    /// Whether food runs away from the snakes. The default is `FoodBehavior::Static`.
+    pub fn food_behavior(mut self, food_behavior: FoodBehavior) -> GameBuilder {
+        self.food_behavior = food_behavior;
+        self
+    }
+
+    /// How many segments the snake grows by for each normal food item it eats, one per tick from
+    /// the tick it eats on. The default is 1.
+    pub fn grow_per_food(mut self, grow_per_food: usize) -> GameBuilder {
+        self.grow_per_food = grow_per_food;
+        self
+    }
+
+    /// How many ticks a cell the tail leaves keeps being drawn as a fading trail before it's
+    /// cleared. The default is 0, which clears it right away.
+    pub fn trail_length(mut self, trail_length: usize) -> GameBuilder {
+        self.trail_length = trail_length;
+        self
+    }
+
+    /// Interior walls, which kill the snake just like the border does.
+    pub fn walls(mut self, walls: HashSet<Coord>) -> GameBuilder {
+        self.walls = walls;
+        self
+    }
+
+    /// Pairs of linked cells: a head that moves onto either end of a pair comes out at the other
+    /// end instead, in the same tick and still heading the same way. The exit counts as where the
+    /// head moved to, so if there's a snake on it, that's the same as running into the snake. Both
+    /// ends must be in bounds, off of the walls and the starting snakes, and not part of another
+    /// pair. There are none by default.
+    pub fn portals(mut self, portals: Vec<(Coord, Coord)>) -> GameBuilder {
+        self.portals = portals;
+        self
+    }
+
+    /// Close the arena in by a ring every `every` ticks: the outermost cells that are still open
+    /// become walls, so the snakes have less and less room. A snake caught on one of them dies,
+    /// unless nothing is deadly in `Mode::Endless`, where the cells under it only become walls once
+    /// it has moved off of them. Food on them moves to a random free cell, and portals with an end
+    /// on them are gone. The innermost ring is never closed, so there's always somewhere left to
+    /// be. The default is 0, which keeps the arena as it is.
+    pub fn shrinking_arena(mut self, every: u64) -> GameBuilder {
+        self.shrink_every = every;
+        self
+    }
+
+    /// Give the snakes momentum: once a snake turns, it has to go on for `ticks` ticks before it
+    /// can turn again. Inputs queued in the meantime wait in the queue until it can, and
+    /// `Game::set_direction` and `Game::step` don't turn the snake until then. The turn a
+    /// safe start makes to keep the snake on the board doesn't count. The default is 0, which lets
+    /// a snake turn every tick.
+    pub fn turn_cooldown(mut self, ticks: u32) -> GameBuilder {
+        self.turn_cooldown = ticks;
+        self
+    }
+
+    #[cfg(feature = "terminal")]
+    pub fn theme(mut self, theme: Theme) -> GameBuilder {
+        self.theme = theme;
+        self
+    }
+
+    /// Add a second player, whose snake starts in the bottom right corner heading up. A snake
+    /// running into the other one dies just like running into itself, and the game is won by the
+    /// last snake alive.
+    pub fn two_player(mut self) -> GameBuilder {
+        self.two_player = true;
+        self
+    }
+
+    /// Start the first player's snake with `body`, from head to tail, moving towards `direction`.
+    /// The segments must be in bounds and each next to the one before it, and `direction` mustn't
+    /// turn the head back onto the second segment. By default the snake is a single segment at
+    /// (0, 0) moving down.
+    pub fn start(mut self, body: Vec<Coord>, direction: Input) -> GameBuilder {
+        self.start = Some((body, direction));
+        self
+    }
+
+    /// Keep the game as it was before each of the last `depth` ticks, so that `Game::undo` can go
+    /// back to it. Each of those is a whole copy of the game, so this is meant for a few dozen
+    /// ticks rather than thousands. The default is 0, which keeps no history.
+    pub fn history(mut self, depth: usize) -> GameBuilder {
+        self.history_depth = depth;
+        self
+    }
+
+    /// Create the game, placing its first food. If the starting snakes leave no room for any, the
+    /// board is already full, so the game starts out won, just like filling it during play.
+    pub fn build(self) -> Result<Game, GameError> {
+        let (height, width) = (self.height, self.width);
         if height < 2 || width < 2 {
-            panic!("Board too small. Must have minimum dimension of 2.")
+            return Err(GameError::BoardTooSmall { height, width });
+        }
+        let (body, direction) = self
+            .start
+            .unwrap_or_else(|| (vec![Coord { x: 0, y: 0 }], Input::DOWN));
+        let mut snakes = vec![Snake::new(body, direction)];
+        if self.two_player {
+            let corner = Coord {
+                x: width as isize - 1,
+                y: height as isize - 1,
+            };
+            snakes.push(Snake::new(vec![corner], Input::UP));
         }
+        let seed = self.seed.unwrap_or_else(|| thread_rng().gen());
         let mut game = Game {
-            snake: vec![Coord { x: 0, y: 0 }],
-            food: None,
+            snakes,
+            food: Vec::new(),
+            food_count: self.food_count,
+            shrink_food_chance: self.shrink_food_chance,
+            food_behavior: self.food_behavior,
+            safe_start: self.safe_start,
+            grow_per_food: self.grow_per_food,
+            trail_length: self.trail_length,
+            trail: Vec::new(),
+            pending_walls: Vec::new(),
+            walls: self.walls,
+            portals: self.portals,
+            shrink_every: self.shrink_every,
+            turn_cooldown: self.turn_cooldown,
             width,
             height,
             state: GameState::RUNNING,
-            cur_input: Input::DOWN,
+            winner: None,
+            death_cause: None,
+            endless_respawn: false,
+            mode: self.mode,
+            win_condition: self.win_condition,
+            wall_mode: self.wall_mode,
+            self_collision: self.self_collision,
+            #[cfg(feature = "terminal")]
+            theme: self.theme,
+            ticks: 0,
+            recording: None,
+            history: VecDeque::new(),
+            history_depth: self.history_depth,
+            initial: None,
+            seed,
+            rng: ChaCha12Rng::seed_from_u64(seed),
         };
+        if let Some(reason) = game.invalid_start() {
+            return Err(GameError::InvalidStart(reason));
+        }
+        if let Some(wall) = game
+            .walls
+            .iter()
+            .find(|wall| !game.coord_is_in_bounds(wall) || game.is_snake(wall))
+        {
+            return Err(GameError::InvalidWall(wall.clone()));
+        }
+        let mut ends = HashSet::new();
+        if let Some(end) = game.portals.iter().flat_map(|(a, b)| [a, b]).find(|&end| {
+            !game.coord_is_in_bounds(end)
+                || game.walls.contains(end)
+                || game.is_snake(end)
+                || !ends.insert(end)
+        }) {
+            return Err(GameError::InvalidPortal(end.clone()));
+        }
         game.place_food();
-        game
+        game.save_initial();
+        Ok(game)
     }
+}
 
-    fn coord_is_in_bounds(&self, coord: &Coord) -> bool {
-        coord.x >= 0 && coord.x < self.width.into() && coord.y >= 0 && coord.y < self.height.into()
+impl Game {
+    pub fn create(height: u16, width: u16) -> Result<Game, GameError> {
+        GameBuilder::new(height, width).build()
     }
 
-    fn get_head(&self) -> &Coord {
-        &self.snake[0]
+    /// Shorthand for `Game::builder(height, width).seed(seed).build()`.
+    pub fn create_seeded(height: u16, width: u16, seed: u64) -> Result<Game, GameError> {
+        GameBuilder::new(height, width).seed(seed).build()
     }
 
-    pub fn get_new_head(&self) -> Coord {
-        let new_head = self.get_head().move_by(&self.cur_input);
-        if self.snake.len() >= 2 && self.snake[1] == new_head {
-            return self.snake[0].move_by(&self.cur_input.rev());
-        }
-        new_head
+    /// Shorthand for `Game::builder(height, width).walls(walls).build()`.
+    pub fn create_with_walls(
+        height: u16,
+        width: u16,
+        walls: HashSet<Coord>,
+    ) -> Result<Game, GameError> {
+        GameBuilder::new(height, width).walls(walls).build()
+    }
+
+    pub fn builder(height: u16, width: u16) -> GameBuilder {
+        GameBuilder::new(height, width)
+    }
+
+    /// The width and height as coordinate values. There's no `From<u16>` for `isize` because it may
+    /// be 16 bits wide, but we don't support platforms like that.
+    fn coord_limits(&self) -> (isize, isize) {
+        (self.width as isize, self.height as isize)
+    }
+
+    fn coord_is_in_bounds(&self, coord: &Coord) -> bool {
+        let (width, height) = self.coord_limits();
+        coord.x >= 0 && coord.x < width && coord.y >= 0 && coord.y < height
     }
 
-    fn place_food(&mut self) -> () {
-        let mut free_coords = HashSet::new();
-        for y in 0..isize::from(self.height) {
-            for x in 0..isize::from(self.width) {
-                free_coords.insert(Coord { x, y });
+    /// What's wrong with the starting snakes, if anything.
+    fn invalid_start(&self) -> Option<String> {
+        let mut seen = HashSet::new();
+        for (player, snake) in self.snakes.iter().enumerate() {
+            if snake.body.is_empty() {
+                return Some("the snake has no segments".to_string());
+            }
+            for (index, segment) in snake.body.iter().enumerate() {
+                if !self.coord_is_in_bounds(segment) {
+                    return Some(format!("segment {} is out of bounds", segment));
+                }
+                if !seen.insert(segment) {
+                    return Some(format!("segment {} overlaps another one", segment));
+                }
+                let is_next_to_previous = index == 0
+                    || INPUTS
+                        .iter()
+                        .any(|input| self.move_on_board(&snake.body[index - 1], input) == *segment);
+                if !is_next_to_previous {
+                    return Some(format!(
+                        "segment {} isn't next to the one before it",
+                        segment
+                    ));
+                }
+            }
+            if self.is_reversal(player, &snake.heading) {
+                return Some(format!(
+                    "moving {:?} turns the head back onto the snake",
+                    snake.heading
+                ));
             }
         }
-        for snake_part in &self.snake {
-            free_coords.remove(&snake_part);
-        }
+        None
+    }
 
-        if free_coords.len() == 0 {
-            self.food = None;
-            return;
-        }
+    fn is_snake(&self, coord: &Coord) -> bool {
+        self.snakes.iter().any(|snake| snake.body.contains(coord))
+    }
 
-        let free_cords = free_coords.into_iter().collect::<Vec<Coord>>();
-        let food_coord = &free_cords[thread_rng().gen_range(0..free_cords.len())];
-        self.food = Some(food_coord.clone())
+    fn is_portal(&self, coord: &Coord) -> bool {
+        self.portals.iter().any(|(a, b)| a == coord || b == coord)
     }
 
-    pub fn tick(&mut self) -> Vec<TermUpdate> {
-        let mut term_updates = Vec::new();
+    /// Where a head that moves onto `coord` ends up: the other end if it's a portal, or else
+    /// `coord` itself.
+    fn through_portal(&self, coord: Coord) -> Coord {
+        for (a, b) in &self.portals {
+            if *a == coord {
+                return b.clone();
+            }
+            if *b == coord {
+                return a.clone();
+            }
+        }
+        coord
+    }
+
+    /// The update that draws `coord` once whatever was on it has left, showing the portal if
+    /// there's one there.
+    fn uncovered(&self, coord: Coord) -> TermUpdate {
+        let type_ = if self.is_portal(&coord) {
+            TermUpdateType::Portal
+        } else {
+            TermUpdateType::Clear
+        };
+        TermUpdate { type_, coord }
+    }
 
-        let new_head = self.get_new_head();
+    /// The seed of the food placement. Games created without one get a random seed.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
 
-        if self.snake[..self.snake.len() - 1].contains(&new_head) {
-            // don't check the last snake part. we want to be able to move into that spot and not die
-            self.state = GameState::DEAD;
-            return term_updates;
+    /// Start recording the inputs given to `queue_input`, so that the game can be replayed later.
+    /// A replay only has the board size, the seed and the inputs, so this fails for a game that
+    /// `Game::create_seeded` wouldn't create the same: one that has ticked already, has other
+    /// settings than the default ones, or has walls, portals, a second player or a starting snake
+    /// of its own. Turning on endless respawn stops the recording.
+    pub fn start_recording(&mut self) -> Result<(), GameError> {
+        if let Some(reason) = self.unreplayable() {
+            return Err(GameError::NotReplayable(reason.to_string()));
         }
+        self.restart_recording();
+        Ok(())
+    }
 
-        self.snake.insert(0, new_head.clone());
-        term_updates.push(TermUpdate {
-            type_: TermUpdateType::Snake,
-            coord: new_head.clone(),
+    /// Start recording over, for a game that `start_recording` has already been called on.
+    fn restart_recording(&mut self) {
+        self.recording = Some(Replay {
+            height: self.height,
+            width: self.width,
+            seed: self.seed,
+            ticks: self.ticks,
+            inputs: Vec::new(),
         });
+    }
 
-        if !self.coord_is_in_bounds(self.get_head()) {
-            self.state = GameState::DEAD;
-            return term_updates;
-        }
-
-        let got_food = match &self.food {
-            Some(food) if self.get_head() == food => {
-                // term_updates.push(TermUpdate {
-                //     type_: TermUpdateType::Clear,
-                //     coord: food.clone(),
-                // });
-                self.place_food();
-                match &self.food {
-                    Some(coord) => term_updates.push(TermUpdate {
-                        type_: TermUpdateType::Food,
-                        coord: coord.clone(),
-                    }),
-                    None => {
-                        // if food is None, that means we couldn't place any food because board is full
-                        // in other words, you've won?
-                        self.state = GameState::WON;
-                        return term_updates;
-                    }
-                }
-                true
-            }
-            _ => false,
-        };
+    /// What this game has that `Game::replay` wouldn't play out the same with, if anything.
+    fn unreplayable(&self) -> Option<&'static str> {
+        let replayed = Game::create_seeded(self.height, self.width, self.seed)
+            .expect("the board size is valid, since this game has it");
+        [
+            ("ticks done", self.ticks != 0),
+            ("a mode", self.mode != replayed.mode),
+            (
+                "a win condition",
+                self.win_condition != replayed.win_condition,
+            ),
+            ("a wall mode", self.wall_mode != replayed.wall_mode),
+            (
+                "a self collision",
+                self.self_collision != replayed.self_collision,
+            ),
+            ("a food count", self.food_count != replayed.food_count),
+            (
+                "shrink food",
+                self.shrink_food_chance != replayed.shrink_food_chance,
+            ),
+            (
+                "a food behavior",
+                self.food_behavior != replayed.food_behavior,
+            ),
+            ("a safe start", self.safe_start != replayed.safe_start),
+            (
+                "growth per food",
+                self.grow_per_food != replayed.grow_per_food,
+            ),
+            ("a trail", self.trail_length != replayed.trail_length),
+            (
+                "a shrinking arena",
+                self.shrink_every != replayed.shrink_every,
+            ),
+            (
+                "a turn cooldown",
+                self.turn_cooldown != replayed.turn_cooldown,
+            ),
+            ("endless respawn", self.endless_respawn),
+            // the walls, the portals, the snakes and the food
+            ("a board of its own", self.encode() != replayed.encode()),
+        ]
+        .into_iter()
+        .find(|(_, differs)| *differs)
+        .map(|(reason, _)| reason)
+    }
 
-        if !got_food {
-            term_updates.push(TermUpdate {
-                type_: TermUpdateType::Clear,
-                coord: self.snake.last().unwrap().clone(),
-            });
-            self.snake.pop();
+    /// The replay recorded since `start_recording`, if any.
+    pub fn recording(&self) -> Option<&Replay> {
+        self.recording.as_ref()
+    }
+
+    /// Play back a recorded game, and return the state it ended up in.
+    pub fn replay(replay: &Replay) -> Result<GameState, GameError> {
+        let mut game = Game::create_seeded(replay.height, replay.width, replay.seed)?;
+        let mut inputs = replay.inputs.iter().peekable();
+        while game.ticks < replay.ticks && game.state == GameState::RUNNING {
+            while let Some((_, input)) = inputs.next_if(|(tick, _)| *tick == game.ticks) {
+                game.queue_input(*input);
+            }
+            game.tick();
         }
+        Ok(game.state)
+    }
 
-        term_updates
+    /// The number of columns of the board, not counting the border. Coordinates on the board have
+    /// an x from 0 up to this.
+    pub fn width(&self) -> u16 {
+        self.width
     }
 
-    /// Draw the initial board to stdout. No clearing is performed.
-    pub fn draw_initial(&self) -> crossterm::Result<()> {
-        let mut stdout = stdout();
+    /// The number of rows of the board, not counting the border. Coordinates on the board have a y
+    /// from 0 up to this.
+    pub fn height(&self) -> u16 {
+        self.height
+    }
 
-        stdout.queue(Clear(ClearType::All))?;
+    /// What happens when a snake moves past the edge of the board.
+    pub fn wall_mode(&self) -> WallMode {
+        self.wall_mode
+    }
 
-        // draw the walls
-        for y in 0..self.height + 2 {
-            for x in 0..self.width + 2 {
-                if y == 0 || y == self.height + 1 || x == 0 || x == self.width + 1 {
-                    stdout
-                        .queue(cursor::MoveTo(x.into(), y.into()))?
-                        .queue(Print(Tile::WALL))?;
-                }
-            }
-        }
+    /// How many ticks the game has done so far. Ticks that end the game count too.
+    pub fn tick_count(&self) -> u64 {
+        self.ticks
+    }
 
-        // draw the snake, offsetting by (+1, +1) for walls
-        for coord in &self.snake {
-            stdout
-                .queue(cursor::MoveTo(
-                    u16::try_from(coord.x).unwrap() + 1,
-                    u16::try_from(coord.y).unwrap() + 1,
-                ))?
-                .queue(Print(Tile::SNAKE))?;
-        }
+    /// How many players there are, each with their own snake. Players are numbered from 0.
+    pub fn player_count(&self) -> usize {
+        self.snakes.len()
+    }
 
-        // draw the food, offsetting by (+1, +1) for walls
-        if let Some(food) = &self.food {
-            stdout
-                .queue(cursor::MoveTo(
-                    u16::try_from(food.x).unwrap() + 1,
-                    u16::try_from(food.y).unwrap() + 1,
-                ))?
-                .queue(Print(Tile::FOOD))?;
-        }
+    /// In a multiplayer game that's been won, the player whose snake outlived the others.
+    pub fn winner(&self) -> Option<usize> {
+        self.winner
+    }
 
-        Ok(())
+    /// What the last snake to die died of, or `None` if none has. If several died in the same
+    /// tick, this is the cause for the lowest-numbered player. With endless respawn this is updated
+    /// on every death, even though the game goes on.
+    pub fn death_cause(&self) -> Option<DeathCause> {
+        self.death_cause
     }
-}
 
-impl fmt::Display for Game {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut tiles = vec![];
-        for _ in 0..self.height {
-            tiles.push(vec![Tile::AIR; self.width.into()])
-        }
+    /// The number of food items eaten so far by the first player. The starting segment doesn't
+    /// count, so a new game has a score of 0. This is kept across endless respawns.
+    pub fn score(&self) -> usize {
+        self.player_score(0)
+    }
 
-        let mut update_coord_tile = |coord: &Coord, tile: Tile| -> () {
-            let x = usize::try_from(coord.x).unwrap();
-            let y = usize::try_from(coord.y).unwrap();
-            tiles[y][x] = tile
-        };
+    /// Like `score`, but for any player.
+    pub fn player_score(&self, player: usize) -> usize {
+        self.snakes[player].food_eaten
+    }
 
-        for snake_part in &self.snake {
-            update_coord_tile(snake_part, Tile::SNAKE);
-        }
-        if let Some(food) = &self.food {
-            update_coord_tile(food, Tile::FOOD);
-        }
+    /// The first food item, or `None` if there isn't any because the board is full.
+    pub fn food(&self) -> Option<&Coord> {
+        self.food.first().map(|food| &food.coord)
+    }
 
-        write!(f, "{}\n", WALL_STR.repeat(usize::from(self.width) + 2))?;
-        for y in 0..usize::from(self.height) {
-            write!(f, "{}", WALL_STR)?;
-            for x in 0..usize::from(self.width) {
-                write!(f, "{}", tiles[y][x])?;
-            }
-            write!(f, "{}\n", WALL_STR)?;
+    /// All of the food items on the board, of any kind. Use `tile_at` to tell them apart.
+    pub fn foods(&self) -> impl Iterator<Item = &Coord> {
+        self.food.iter().map(|food| &food.coord)
+    }
+
+    /// Put normal food at `coord` in place of the first food item, e.g. to set up a scenario, or add
+    /// it if there's no food on the board. Fails, leaving the food as it was, if `coord` is out of
+    /// bounds or has a snake, a wall, other food or a portal on it.
+    pub fn set_food(&mut self, coord: Coord) -> Result<(), GameError> {
+        if !self.coord_is_in_bounds(&coord)
+            || self.is_snake(&coord)
+            || self.walls.contains(&coord)
+            || self.food_at(&coord).is_some()
+            || self.is_portal(&coord)
+        {
+            return Err(GameError::InvalidFood(coord));
+        }
+        let food = Food {
+            coord,
+            kind: FoodKind::Normal,
+        };
+        match self.food.first_mut() {
+            Some(first) => *first = food,
+            None => self.food.push(food),
         }
-        write!(f, "{}\n", WALL_STR.repeat(usize::from(self.width) + 2))?;
         Ok(())
     }
-}
 
-pub struct InteractiveGame {
-    game_mut: Arc<Mutex<Game>>,
-    tick_wait: time::Duration,
-}
-impl InteractiveGame {
-    pub fn play(height: u8, width: u8, tick_wait: time::Duration) -> () {
-        let ig = InteractiveGame {
-            game_mut: Arc::new(Mutex::new(Game::create(height, width))),
-            tick_wait,
-        };
+    fn food_at(&self, coord: &Coord) -> Option<&Food> {
+        self.food.iter().find(|food| food.coord == *coord)
+    }
 
-        let ticker_mut = Arc::clone(&ig.game_mut);
-        let ticker = thread::spawn(move || {
-            // - print the board
-            // - wait
-            // - tick
-            let mut stdout = stdout();
-            stdout.queue(Hide).unwrap();
-            ticker_mut.lock().unwrap().draw_initial().unwrap();
-            stdout.flush().unwrap();
-
-            let mut term_updates: Vec<TermUpdate> = Vec::new();
-            loop {
-                {
-                    // let game = ticker_mut.lock().unwrap();
-                    for term_update in &term_updates {
-                        term_update.queue(&mut stdout).unwrap();
-                    }
-                    stdout.flush().unwrap();
-                }
-                thread::sleep(ig.tick_wait);
-                {
-                    let mut game = ticker_mut.lock().unwrap();
+    /// The number of segments in the first player's snake, including the head.
+    pub fn snake_len(&self) -> usize {
+        self.snakes[0].body.len()
+    }
 
-                    term_updates = game.tick();
+    /// The head of the first player's snake.
+    pub fn head(&self) -> &Coord {
+        self.snakes[0].head()
+    }
 
-                    if game.state != GameState::RUNNING {
-                        println!("{:?}", game.state);
-                        break;
-                    }
-                }
-            }
-        });
+    /// The segments of the first player's snake, head first and tail last.
+    pub fn body(&self) -> impl Iterator<Item = &Coord> {
+        self.snakes[0].body.iter()
+    }
 
-        let input_handler_mut = Arc::clone(&ig.game_mut);
-        let input_handler = thread::spawn(move || loop {
-            if poll(ig.tick_wait).unwrap() {
-                let event = read().unwrap();
-                let input = match event {
-                    Event::Key(KeyEvent { modifiers: _, code }) => match code {
-                        Up | Char('w') | Char('W') => Some(Input::UP),
-                        Left | Char('a') | Char('A') => Some(Input::LEFT),
-                        Down | Char('s') | Char('S') => Some(Input::DOWN),
-                        Right | Char('d') | Char('D') => Some(Input::RIGHT),
-                        _ => None,
-                    },
-                    _ => None,
-                };
-                if let Some(i) = input {
-                    let mut game = input_handler_mut.lock().unwrap();
-                    game.cur_input = i;
-                }
-            } else if input_handler_mut.lock().unwrap().state != GameState::RUNNING {
-                break;
-            }
-        });
+    /// The smallest box containing the whole snake of the first player, as its (min x, min y) and
+    /// (max x, max y) corners. Both corners are inclusive.
+    pub fn snake_bounds(&self) -> (Coord, Coord) {
+        let head = self.head();
+        let mut min = head.clone();
+        let mut max = head.clone();
+        for snake_part in &self.snakes[0].body[1..] {
+            min.x = min.x.min(snake_part.x);
+            min.y = min.y.min(snake_part.y);
+            max.x = max.x.max(snake_part.x);
+            max.y = max.y.max(snake_part.y);
+        }
+        (min, max)
+    }
+
+    /// Move `coord` one cell towards `input`, wrapping around the board edges in `WallMode::Wrap`.
+    fn move_on_board(&self, coord: &Coord, input: &Input) -> Coord {
+        let moved = coord.move_by(input);
+        let (width, height) = self.coord_limits();
+        match self.wall_mode {
+            WallMode::Solid => moved,
+            WallMode::Wrap => Coord {
+                x: moved.x.rem_euclid(width),
+                y: moved.y.rem_euclid(height),
+            },
+        }
+    }
+
+    /// Whether `input` would turn `player`'s snake back onto its own neck. Reversals are rejected
+    /// and the snake keeps its heading. A length-1 snake has no neck, so it can turn whichever way.
+    fn is_reversal(&self, player: usize, input: &Input) -> bool {
+        let snake = &self.snakes[player];
+        snake.body.len() >= 2 && self.move_on_board(snake.head(), input) == snake.body[1]
+    }
+
+    /// The direction `player`'s snake will move in next tick, which is its `cur_input` unless
+    /// that's a reversal.
+    fn effective_input(&self, player: usize) -> Input {
+        let cur_input = self.snakes[player].cur_input;
+        if self.is_reversal(player, &cur_input) {
+            cur_input.rev()
+        } else {
+            cur_input
+        }
+    }
+
+    /// The direction the first player's snake last moved in, or the one it starts in if it hasn't
+    /// moved yet.
+    pub fn heading(&self) -> &Input {
+        &self.snakes[0].heading
+    }
+
+    /// Whether the first player's snake can turn towards `input`, i.e. whether it isn't an
+    /// immediate reversal, a move onto the segment behind the head. A length-1 snake has no such
+    /// segment, so it may even turn back the way it came.
+    pub fn is_legal_move(&self, input: &Input) -> bool {
+        !self.is_reversal(0, input)
+    }
+
+    /// Where the first player's snake's head will be after the next tick, having gone through a
+    /// portal if it moves onto one.
+    pub fn get_new_head(&self) -> Coord {
+        self.get_player_new_head(0)
+    }
+
+    fn get_player_new_head(&self, player: usize) -> Coord {
+        let snake = &self.snakes[player];
+        self.through_portal(self.move_on_board(snake.head(), &self.effective_input(player)))
+    }
+
+    /// Pick a random coordinate that is occupied by neither a snake, the food, a wall nor a portal,
+    /// or `None` if there isn't one. Every free coordinate is equally likely.
+    fn random_free_coord(&mut self) -> Option<Coord> {
+        let occupied: HashSet<&Coord> = self
+            .snakes
+            .iter()
+            .flat_map(|snake| &snake.body)
+            .chain(self.food.iter().map(|food| &food.coord))
+            .chain(&self.walls)
+            .chain(self.portals.iter().flat_map(|(a, b)| [a, b]))
+            .collect();
+        let (width, height) = self.coord_limits();
+
+        // while the board is mostly empty, a random cell is very likely to be free, so try a few of
+        // those before paying for a walk over the whole board
+        for _ in 0..RANDOM_FREE_COORD_TRIES {
+            let coord = Coord {
+                x: self.rng.gen_range(0..width),
+                y: self.rng.gen_range(0..height),
+            };
+            if !occupied.contains(&coord) {
+                return Some(coord);
+            }
+        }
+
+        // collected in row-major order (rather than through a HashSet) so that a seeded rng always
+        // picks the same cell
+        let mut free_coords = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let coord = Coord { x, y };
+                if !occupied.contains(&coord) {
+                    free_coords.push(coord);
+                }
+            }
+        }
+
+        if free_coords.is_empty() {
+            return None;
+        }
+
+        let index = self.rng.gen_range(0..free_coords.len());
+        Some(free_coords.swap_remove(index))
+    }
+
+    /// Top the food up to `food_count`, as far as there is room for it. Returns the newly placed
+    /// food. If there's no food at all afterwards, the board is full, so the game is won.
+    ///
+    /// Food is never placed on any segment of any snake, on a wall, on other food or on a portal,
+    /// however the snakes started out.
+    fn place_food(&mut self) -> Vec<Food> {
+        let mut placed = Vec::new();
+        while self.food.len() < self.food_count {
+            let coord = match self.random_free_coord() {
+                Some(coord) => coord,
+                None => break,
+            };
+            debug_assert!(
+                !self.is_snake(&coord)
+                    && !self.walls.contains(&coord)
+                    && self.food_at(&coord).is_none(),
+                "food placed on an occupied cell {}",
+                coord
+            );
+            // only draw from the rng when there's a chance of shrink food, so that seeded games
+            // without it play out the same as before it existed
+            let kind =
+                if self.shrink_food_chance > 0.0 && self.rng.gen_bool(self.shrink_food_chance) {
+                    FoodKind::Shrink
+                } else {
+                    FoodKind::Normal
+                };
+            let food = Food { coord, kind };
+            self.food.push(food.clone());
+            placed.push(food);
+        }
+        if self.food.is_empty() {
+            self.state = GameState::WON;
+        }
+        placed
+    }
+
+    /// Keep the game as it is now as what `reset` goes back to.
+    fn save_initial(&mut self) {
+        self.initial = None;
+        self.initial = Some(Arc::new(self.clone()));
+    }
+
+    /// Put the game back the way it was when it was created: the snakes where they started and
+    /// heading the way they did, the walls, portals and food as they were, and no ticks done.
+    /// Settings that can be changed after creating the game are kept, namely endless respawn and
+    /// the theme, and so is recording, which starts over.
+    ///
+    /// The random number generator starts over from the same seed, so the game plays out exactly
+    /// as it did the first time, food and all, given the same inputs. Nothing is drawn, so redraw
+    /// the board with `redraw_all` afterwards. With serde, what the game was like when it was
+    /// created isn't serialized, so this does nothing for a deserialized game.
+    pub fn reset(&mut self) {
+        let Some(initial) = self.initial.take() else {
+            return;
+        };
+        let mut game = Game::clone(&initial);
+        game.initial = Some(initial);
+        game.endless_respawn = self.endless_respawn;
+        #[cfg(feature = "terminal")]
+        {
+            // e.g. with the truecolor fallback `InteractiveGame::run` applies
+            game.theme = self.theme.clone();
+        }
+        let recording = self.recording.is_some();
+        *self = game;
+        if recording {
+            self.restart_recording();
+        }
+    }
+
+    /// Give this game, which must not have ticked yet, a new random seed, and place the food again
+    /// accordingly.
+    #[cfg(feature = "terminal")]
+    fn reseed(&mut self) {
+        self.seed = thread_rng().gen();
+        self.rng = ChaCha12Rng::seed_from_u64(self.seed);
+        self.food.clear();
+        self.place_food();
+        if self.recording.is_some() {
+            self.restart_recording();
+        }
+    }
+
+    /// Enable or disable endless respawn. When enabled, a death doesn't end the game: the snake is
+    /// relocated as a single segment to a random free cell, and the food stays where it is.
+    ///
+    /// This is meant for continuous practice and is different from `reset`, which puts the snake
+    /// back where it started. Replays don't respawn, so enabling this stops any recording.
+    pub fn set_endless_respawn(&mut self, enabled: bool) {
+        self.endless_respawn = enabled;
+        if enabled {
+            self.recording = None;
+        }
+    }
+
+    /// Relocate `player`'s snake for an endless respawn, pushing the updates needed to draw that
+    /// onto `term_updates`. Returns `false`, without changing anything, if there's no room for it.
+    fn respawn(&mut self, player: usize, term_updates: &mut Vec<TermUpdate>) -> bool {
+        let old_body = std::mem::take(&mut self.snakes[player].body);
+        let new_head = match self.random_free_coord() {
+            Some(coord) => coord,
+            None => {
+                self.snakes[player].body = old_body;
+                return false;
+            }
+        };
+
+        // the new head covers any trail under it, and the old body is cleared below, so neither
+        // is left to fade out
+        self.trail
+            .retain(|(coord, _)| *coord != new_head && !old_body.contains(coord));
+        for snake_part in old_body {
+            // another snake may have moved in as this one died, e.g. onto its tail
+            if !self.is_snake(&snake_part) {
+                term_updates.push(self.uncovered(snake_part));
+            }
+        }
+        term_updates.push(TermUpdate {
+            type_: TermUpdateType::SnakeHead(player),
+            coord: new_head.clone(),
+        });
+        self.snakes[player].body = vec![new_head];
+        self.snakes[player].pending_growth = 0;
+        true
+    }
+
+    /// Turn the snake towards `input` and advance the game by one tick, returning the resulting
+    /// state. A reversal is ignored and the snake keeps its heading, same as in interactive play,
+    /// and so is a turn before the turn cooldown is over.
+    ///
+    /// The `TermUpdate`s of the tick are discarded, so this is the way to drive the game without a
+    /// terminal.
+    pub fn step(&mut self, input: Input) -> GameState {
+        self.set_direction(input);
+        self.tick();
+        self.state
+    }
+
+    /// Turn the first player's snake towards `input` right away, without advancing the game. Returns
+    /// `false`, leaving the direction as it was, if `input` is a reversal, or if there's a turn
+    /// cooldown that isn't over yet. Unlike `queue_input`, this replaces any direction set since
+    /// the last tick, except with a turn cooldown, where that turn starts the cooldown too.
+    pub fn set_direction(&mut self, input: Input) -> bool {
+        if !self.is_legal_move(&input) {
+            return false;
+        }
+        // the turn is made by the next tick
+        self.turn(0, input, self.ticks + 1)
+    }
+
+    /// Turn `player`'s snake towards `input` on tick `tick`, if the turn cooldown since its last
+    /// turn is over by then. Returns whether it was. Keeping the direction it's already going in
+    /// isn't a turn, so that's always fine.
+    fn turn(&mut self, player: usize, input: Input, tick: u64) -> bool {
+        let snake = &mut self.snakes[player];
+        if input == snake.cur_input {
+            return true;
+        }
+        if snake
+            .last_turn
+            .is_some_and(|last_turn| tick - last_turn < u64::from(self.turn_cooldown))
+        {
+            return false;
+        }
+        snake.cur_input = input;
+        snake.last_turn = Some(tick);
+        true
+    }
+
+    /// Step the game with each of `inputs` in turn, like `step` does, until they run out or the
+    /// game ends. With a seeded game, this always plays out the same, so it's a way to check what
+    /// a given sequence of moves leads to without a terminal.
+    pub fn run_script(&mut self, inputs: &[Input]) -> GameSummary {
+        for input in inputs {
+            if self.state != GameState::RUNNING {
+                break;
+            }
+            self.step(*input);
+        }
+        GameSummary {
+            state: self.state,
+            score: self.score(),
+            ticks: self.ticks,
+        }
+    }
+
+    /// The state that stepping with each of `inputs` in turn would leave the game in, like
+    /// `run_script`, but played out on a copy so that this game is left as it is. Nothing is drawn.
+    ///
+    /// The copy carries on from this game's random number generator, so any food placed along
+    /// the way lands where it would if the same moves were really played.
+    pub fn simulate(&self, inputs: &[Input]) -> GameState {
+        self.clone().run_script(inputs).state
+    }
+
+    /// Queue a direction change for the first player, to be applied by a later tick, one per tick,
+    /// so that quick successive turns aren't lost. Each input is checked against the snake's
+    /// heading at the time it's applied: one that would reverse the snake, or that doesn't change
+    /// its heading, is dropped. If the queue is full, `input` is dropped. With a turn cooldown,
+    /// inputs aren't applied until the cooldown since the snake's last turn is over.
+    pub fn queue_input(&mut self, input: Input) {
+        if let Some(recording) = &mut self.recording {
+            recording.inputs.push((self.ticks, input));
+        }
+        self.queue_player_input(0, input);
+    }
+
+    /// Like `queue_input`, but for any player.
+    pub fn queue_player_input(&mut self, player: usize, input: Input) {
+        let input_queue = &mut self.snakes[player].input_queue;
+        if input_queue.len() < INPUT_QUEUE_CAPACITY {
+            input_queue.push_back(input);
+        }
+    }
+
+    fn apply_queued_input(&mut self, player: usize) {
+        while let Some(&input) = self.snakes[player].input_queue.front() {
+            if input == self.snakes[player].cur_input || self.is_reversal(player, &input) {
+                self.snakes[player].input_queue.pop_front();
+                continue;
+            }
+            if self.turn(player, input, self.ticks) {
+                self.snakes[player].input_queue.pop_front();
+            }
+            // otherwise it's still going on from the last turn, so the input waits
+            return;
+        }
+    }
+
+    /// Turn `player`'s snake to the first direction that isn't a reversal and that keeps it on the
+    /// board, if it's headed off of it. If there's no such direction, it's left as it is.
+    fn keep_on_board(&mut self, player: usize) {
+        let head = self.snakes[player].head();
+        if self.coord_is_in_bounds(&self.get_player_new_head(player)) {
+            return;
+        }
+        if let Some(input) = INPUTS.into_iter().find(|input| {
+            !self.is_reversal(player, input)
+                && self.coord_is_in_bounds(&self.move_on_board(head, input))
+        }) {
+            self.snakes[player].cur_input = input;
+        }
+    }
+
+    /// Move each food item that's at most `radius` cells away from the head of a snake to a random
+    /// free cell, with a chance of `SKITTISH_FOOD_CHANCE`, pushing the updates needed to draw that.
+    /// Food that has nowhere to go stays put.
+    fn scare_food(&mut self, radius: usize, term_updates: &mut Vec<TermUpdate>) {
+        for index in 0..self.food.len() {
+            let coord = &self.food[index].coord;
+            let near = self
+                .snakes
+                .iter()
+                .any(|snake| snake.head().manhattan_distance(coord) <= radius);
+            if !near || !self.rng.gen_bool(SKITTISH_FOOD_CHANCE) {
+                continue;
+            }
+            let old_coord = self.food[index].coord.clone();
+            if self.relocate_food(index, term_updates) {
+                term_updates.push(TermUpdate {
+                    type_: TermUpdateType::Clear,
+                    coord: old_coord,
+                });
+            }
+        }
+    }
+
+    /// Move food item `index` to a random free cell, pushing the update that draws it there, and
+    /// return `true`, or return `false` without moving it if there's no free cell. The cell it
+    /// leaves is up to the caller.
+    fn relocate_food(&mut self, index: usize, term_updates: &mut Vec<TermUpdate>) -> bool {
+        // the food is still in the way, so it can't land back where it was
+        let Some(coord) = self.random_free_coord() else {
+            return false;
+        };
+        self.food[index].coord = coord.clone();
+        term_updates.push(TermUpdate {
+            type_: TermUpdateType::Food(self.food[index].kind),
+            coord,
+        });
+        true
+    }
+
+    /// Whether `player`'s snake keeps its tail in place when its head moves to `new_head`, because
+    /// it's eating or still has growth pending. Eating shrink food takes the tail off regardless.
+    fn is_growing(&self, player: usize, new_head: &Coord) -> bool {
+        let pending_growth = self.snakes[player].pending_growth;
+        match self.food_at(new_head).map(|food| food.kind) {
+            Some(FoodKind::Normal) => pending_growth + self.grow_per_food > 0,
+            Some(FoodKind::Shrink) => false,
+            None => pending_growth > 0,
+        }
+    }
+
+    /// Whether each snake keeps its tail in place when the heads move to `new_heads`.
+    fn growing(&self, new_heads: &[Coord]) -> Vec<bool> {
+        // in endless mode a snake may stay put, tail and all, so no tail is safe to move into
+        (0..self.snakes.len())
+            .map(|player| self.mode == Mode::Endless || self.is_growing(player, &new_heads[player]))
+            .collect()
+    }
+
+    /// The directions the first player's snake can move in next tick without dying, in the order
+    /// up, down, left, right. A reversal is never one of them, and none are left when the snake is
+    /// boxed in. The other snakes are assumed to keep going the way they're headed.
+    pub fn legal_moves(&self) -> Vec<Input> {
+        INPUTS
+            .into_iter()
+            .filter(|input| {
+                if self.is_reversal(0, input) {
+                    return false;
+                }
+                let mut new_heads: Vec<Coord> = (0..self.snakes.len())
+                    .map(|player| self.get_player_new_head(player))
+                    .collect();
+                new_heads[0] = self.through_portal(self.move_on_board(self.head(), input));
+                let growing = self.growing(&new_heads);
+                self.death_cause_of(0, &new_heads, &growing).is_none()
+            })
+            .collect()
+    }
+
+    /// What `player`'s snake dies of when its head moves, or `None` if it survives. `new_heads` are
+    /// where every snake's head moves to, and `growing` whether each snake is about to eat. A
+    /// snake's tail is out of the way by the time anything moves into it, unless the snake is
+    /// growing.
+    fn death_cause_of(
+        &self,
+        player: usize,
+        new_heads: &[Coord],
+        growing: &[bool],
+    ) -> Option<DeathCause> {
+        let new_head = &new_heads[player];
+        if !self.coord_is_in_bounds(new_head) {
+            return Some(DeathCause::Border);
+        }
+        if self.walls.contains(new_head) {
+            return Some(DeathCause::Obstacle);
+        }
+        // a snake that bites itself survives too, it's cut short afterwards
+        let passable = self.self_collision != SelfCollision::Deadly || self.mode == Mode::Endless;
+        for (other, snake) in self.snakes.iter().enumerate() {
+            if other == player && passable {
+                continue;
+            }
+            let body = if growing[other] {
+                &snake.body[..]
+            } else {
+                &snake.body[..snake.body.len() - 1]
+            };
+            if body.contains(new_head) || (other != player && new_heads[other] == *new_head) {
+                return Some(if other == player {
+                    DeathCause::OwnBody
+                } else {
+                    DeathCause::OtherSnake
+                });
+            }
+        }
+        None
+    }
+
+    /// Advance the game by one tick, returning the updates needed to draw what changed. Like
+    /// `tick_into`, but with a new buffer every time.
+    pub fn tick(&mut self) -> Vec<TermUpdate> {
+        let mut term_updates = Vec::new();
+        self.tick_into(&mut term_updates);
+        term_updates
+    }
+
+    /// Advance the game by one tick, writing the updates needed to draw what changed into
+    /// `term_updates`, which is cleared first. Passing the same buffer every tick saves allocating
+    /// a new one each time.
+    ///
+    /// Every cell that changes gets an update, and the last one for a cell is what it shows now, so
+    /// applying them in order keeps any renderer in sync with the board. For example, the cell of
+    /// eaten food gets the head of the snake that ate it.
+    pub fn tick_into(&mut self, term_updates: &mut Vec<TermUpdate>) {
+        term_updates.clear();
+        self.push_history();
+
+        self.ticks += 1;
+        if let Some(recording) = &mut self.recording {
+            recording.ticks = self.ticks;
+        }
+        if let FoodBehavior::Skittish { radius } = self.food_behavior {
+            self.scare_food(radius, term_updates);
+        }
+        if self.shrink_every > 0 && self.ticks.is_multiple_of(self.shrink_every) {
+            let ring = (self.ticks / self.shrink_every - 1) as isize;
+            if self.shrink_arena(ring, term_updates) {
+                return;
+            }
+        }
+
+        let players = 0..self.snakes.len();
+        let mut new_heads = Vec::new();
+        let mut headings = Vec::new();
+        for player in players.clone() {
+            self.apply_queued_input(player);
+            if self.safe_start && self.ticks == 1 {
+                self.keep_on_board(player);
+            }
+            new_heads.push(self.get_player_new_head(player));
+            headings.push(self.effective_input(player));
+        }
+        let growing = self.growing(&new_heads);
+        let death_causes: Vec<Option<DeathCause>> = players
+            .clone()
+            .map(|player| self.death_cause_of(player, &new_heads, &growing))
+            .collect();
+        let (mut dead, alive): (Vec<usize>, Vec<usize>) = players
+            .clone()
+            .partition(|&player| death_causes[player].is_some());
+        if self.mode == Mode::Endless {
+            // the snakes that would have died stay put instead
+            dead.clear();
+        }
+        if let Some(&first_dead) = dead.first() {
+            self.death_cause = death_causes[first_dead];
+        }
+        // a snake that stays put keeps its heading
+        for &player in alive.iter().chain(&dead) {
+            self.snakes[player].heading = headings[player];
+        }
+
+        if !dead.is_empty() && !self.endless_respawn {
+            if alive.len() == 1 && self.snakes.len() > 1 {
+                self.winner = Some(alive[0]);
+                self.state = GameState::WON;
+            } else {
+                self.state = GameState::DEAD;
+            }
+            return;
+        }
+
+        let mut ate = false;
+        let mut vacated = Vec::new();
+        for &player in &alive {
+            let new_head = new_heads[player].clone();
+            // the old head is part of the body now. if it's the tail as well, it's cleared below
+            term_updates.push(TermUpdate {
+                type_: TermUpdateType::Snake(player),
+                coord: self.snakes[player].head().clone(),
+            });
+            self.snakes[player].body.insert(0, new_head.clone());
+
+            // the eaten food's cell gets an update anyway, the new head pushed below, so it needs
+            // no clear of its own
+            let eaten = self.food.iter().position(|food| food.coord == new_head);
+            let eaten = eaten.map(|index| self.food.remove(index).kind);
+            ate |= eaten.is_some();
+            if eaten.is_some() && self.mode == Mode::Hard {
+                self.pending_walls.push(new_head.clone());
+            }
+            let snake = &mut self.snakes[player];
+            if eaten == Some(FoodKind::Normal) {
+                snake.food_eaten += 1;
+                snake.pending_growth += self.grow_per_food;
+            }
+            let shrink_by = match eaten {
+                Some(FoodKind::Shrink) => 1 + SHRINK_FOOD_SEGMENTS,
+                _ if snake.pending_growth > 0 => {
+                    snake.pending_growth -= 1;
+                    0
+                }
+                _ => 1,
+            };
+            let body = &mut snake.body;
+            for _ in 0..shrink_by.min(body.len() - 1) {
+                vacated.push(body.pop().unwrap());
+            }
+            if self.self_collision == SelfCollision::Bite && self.mode != Mode::Endless {
+                // the bitten segment is under the new head, so it isn't cleared with the rest
+                if let Some(bitten) = body[1..].iter().position(|coord| *coord == new_head) {
+                    vacated.extend(body.drain(bitten + 1..));
+                }
+            }
+        }
+        // the new heads go after every old one, so that the old head of a snake that's just left a
+        // cell isn't drawn over the head of another one that moved in
+        for &player in &alive {
+            term_updates.push(TermUpdate {
+                type_: TermUpdateType::SnakeHead(player),
+                coord: self.snakes[player].head().clone(),
+            });
+        }
+
+        self.raise_walls(&mut vacated, term_updates);
+
+        for &player in &dead {
+            if !self.respawn(player, term_updates) {
+                self.state = GameState::DEAD;
+                return;
+            }
+        }
+
+        if ate {
+            for food in self.place_food() {
+                term_updates.push(TermUpdate {
+                    type_: TermUpdateType::Food(food.kind),
+                    coord: food.coord,
+                });
+            }
+        }
+
+        self.update_trail(vacated, term_updates);
+        self.check_win_condition();
+    }
+
+    /// Keep a copy of the game as it is now for `undo`, dropping the oldest one past the history
+    /// depth.
+    fn push_history(&mut self) {
+        if self.history_depth == 0 {
+            return;
+        }
+        // the copy doesn't need a history of its own, it's kept in this one's
+        let mut history = std::mem::take(&mut self.history);
+        history.push_back(self.clone());
+        if history.len() > self.history_depth {
+            history.pop_front();
+        }
+        self.history = history;
+    }
+
+    /// Put the game back the way it was before the last tick, snakes, food, score, queued inputs
+    /// and all, and return `true`, or return `false` without changing anything if there's no
+    /// history left to go back to. See `GameBuilder::history`. The random number generator goes
+    /// back too, so playing the same moves again places the same food. Nothing is drawn, so
+    /// redraw the board with `redraw_all` afterwards. With serde, the history isn't serialized.
+    pub fn undo(&mut self) -> bool {
+        let Some(previous) = self.history.pop_back() else {
+            return false;
+        };
+        let history = std::mem::take(&mut self.history);
+        *self = previous;
+        self.history = history;
+        true
+    }
+
+    /// Wall off ring `ring` of the arena, counting the outermost as 0, pushing the updates needed to
+    /// draw that, unless it's the innermost ring or there's no such ring. Snakes caught in it die
+    /// or respawn like on any other death, or wait for it in `Mode::Endless`. Returns whether that
+    /// ended the game.
+    fn shrink_arena(&mut self, ring: isize, term_updates: &mut Vec<TermUpdate>) -> bool {
+        let (width, height) = self.coord_limits();
+        if 2 * (ring + 1) >= width.min(height) {
+            return false;
+        }
+        let mut cells = Vec::new();
+        for y in ring..height - ring {
+            for x in ring..width - ring {
+                let coord = Coord { x, y };
+                let on_ring =
+                    x == ring || x == width - 1 - ring || y == ring || y == height - 1 - ring;
+                if on_ring && !self.walls.contains(&coord) {
+                    cells.push(coord);
+                }
+            }
+        }
+
+        if self.mode == Mode::Endless {
+            // nothing is deadly, so the cells under a snake wait for it to move off of them
+            let (pending, open): (Vec<Coord>, Vec<Coord>) =
+                cells.into_iter().partition(|coord| self.is_snake(coord));
+            self.pending_walls.extend(pending);
+            cells = open;
+        }
+        let caught: Vec<usize> = (0..self.snakes.len())
+            .filter(|&player| {
+                self.snakes[player]
+                    .body
+                    .iter()
+                    .any(|coord| cells.contains(coord))
+            })
+            .collect();
+
+        self.walls.extend(cells.iter().cloned());
+        self.trail.retain(|(coord, _)| !cells.contains(coord));
+        self.pending_walls.retain(|coord| !cells.contains(coord));
+        let (closed, portals): (Vec<_>, Vec<_>) = std::mem::take(&mut self.portals)
+            .into_iter()
+            .partition(|(a, b)| cells.contains(a) || cells.contains(b));
+        self.portals = portals;
+        for end in closed.into_iter().flat_map(|(a, b)| [a, b]) {
+            if !cells.contains(&end) && !self.is_snake(&end) {
+                term_updates.push(self.uncovered(end));
+            }
+        }
+        for index in 0..self.food.len() {
+            if !cells.contains(&self.food[index].coord) {
+                continue;
+            }
+            // the ring's cells are drawn as walls below
+            self.relocate_food(index, term_updates);
+        }
+        // food that had nowhere to go is gone, and topped up below if there's room after all
+        self.food.retain(|food| !cells.contains(&food.coord));
+
+        let mut ended = false;
+        if !caught.is_empty() {
+            self.death_cause = Some(DeathCause::Obstacle);
+            if self.endless_respawn {
+                ended = !caught
+                    .iter()
+                    .all(|&player| self.respawn(player, term_updates));
+                if ended {
+                    self.state = GameState::DEAD;
+                }
+            } else {
+                ended = true;
+                let survivors: Vec<usize> = (0..self.snakes.len())
+                    .filter(|player| !caught.contains(player))
+                    .collect();
+                if survivors.len() == 1 && self.snakes.len() > 1 {
+                    self.winner = Some(survivors[0]);
+                    self.state = GameState::WON;
+                } else {
+                    self.state = GameState::DEAD;
+                }
+            }
+        }
+
+        // after the respawns, so that food doesn't take the room they need
+        if !ended {
+            for food in self.place_food() {
+                term_updates.push(TermUpdate {
+                    type_: TermUpdateType::Food(food.kind),
+                    coord: food.coord,
+                });
+            }
+            ended = self.state != GameState::RUNNING;
+        }
+
+        // after the respawns, which clear where the snakes were
+        term_updates.extend(cells.into_iter().map(|coord| TermUpdate {
+            type_: TermUpdateType::Wall,
+            coord,
+        }));
+        ended
+    }
+
+    /// End the game as won if the win condition has been met. Filling the board is handled by
+    /// `place_food`, since that's where it shows.
+    fn check_win_condition(&mut self) {
+        if self.state != GameState::RUNNING {
+            return;
+        }
+        match self.win_condition {
+            WinCondition::FillBoard => {}
+            WinCondition::Length(length) => {
+                if let Some(player) = self
+                    .snakes
+                    .iter()
+                    .position(|snake| snake.body.len() >= length)
+                {
+                    if self.snakes.len() > 1 {
+                        self.winner = Some(player);
+                    }
+                    self.state = GameState::WON;
+                }
+            }
+            WinCondition::Ticks(ticks) => {
+                if self.ticks >= ticks {
+                    self.state = GameState::WON;
+                }
+            }
+        }
+    }
+
+    /// Turn the cells food was eaten on in hard mode into walls once no snake is on them anymore,
+    /// pushing the updates needed to draw that. They're taken out of `vacated` so that they aren't
+    /// cleared. This runs before any food is placed, so that none lands on a cell that's about to
+    /// become a wall.
+    fn raise_walls(&mut self, vacated: &mut Vec<Coord>, term_updates: &mut Vec<TermUpdate>) {
+        let mut pending_walls = std::mem::take(&mut self.pending_walls);
+        pending_walls.retain(|coord| {
+            if self.is_snake(coord) || self.food_at(coord).is_some() {
+                return true;
+            }
+            vacated.retain(|vacated| vacated != coord);
+            term_updates.push(TermUpdate {
+                type_: TermUpdateType::Wall,
+                coord: coord.clone(),
+            });
+            self.walls.insert(coord.clone());
+            false
+        });
+        self.pending_walls = pending_walls;
+    }
+
+    /// Age the trail by a tick and add the cells the tails just left to it, pushing the updates
+    /// needed to draw that. Without a trail, the vacated cells are cleared right away, and portals
+    /// never leave a trail, they're just drawn again. This runs
+    /// after everything else has moved, so that no cell something has moved into is drawn over.
+    /// Cells that are still covered, e.g. by a passable snake's overlapping segments, are skipped.
+    fn update_trail(&mut self, vacated: Vec<Coord>, term_updates: &mut Vec<TermUpdate>) {
+        let mut uncovered: Vec<Coord> = Vec::new();
+        for coord in vacated {
+            // overlapping segments can leave the same cell more than once
+            if !self.is_snake(&coord)
+                && self.food_at(&coord).is_none()
+                && !uncovered.contains(&coord)
+            {
+                uncovered.push(coord);
+            }
+        }
+        let (portals, vacated): (Vec<Coord>, Vec<Coord>) = uncovered
+            .into_iter()
+            .partition(|coord| self.is_portal(coord));
+        for coord in portals {
+            term_updates.push(self.uncovered(coord));
+        }
+        if self.trail_length == 0 {
+            term_updates.extend(vacated.into_iter().map(|coord| TermUpdate {
+                type_: TermUpdateType::Clear,
+                coord,
+            }));
+            return;
+        }
+
+        let mut trail = std::mem::take(&mut self.trail);
+        trail.retain_mut(|(coord, age)| {
+            *age += 1;
+            if self.is_snake(coord) || self.food_at(coord).is_some() {
+                // already drawn over
+                false
+            } else if *age >= self.trail_length {
+                term_updates.push(TermUpdate {
+                    type_: TermUpdateType::Clear,
+                    coord: coord.clone(),
+                });
+                false
+            } else {
+                true
+            }
+        });
+        for coord in vacated {
+            term_updates.push(TermUpdate {
+                type_: TermUpdateType::Trail,
+                coord: coord.clone(),
+            });
+            // a cell left again before it has faded out starts over, rather than fading out twice
+            match trail.iter_mut().find(|(trail, _)| *trail == coord) {
+                Some((_, age)) => *age = 0,
+                None => trail.push((coord, 0)),
+            }
+        }
+        self.trail = trail;
+    }
+
+    /// The tile at `coord`, as the board is rendered. Coordinates outside of the board are walls.
+    pub fn tile_at(&self, coord: &Coord) -> Tile {
+        if !self.coord_is_in_bounds(coord) || self.walls.contains(coord) {
+            return Tile::WALL;
+        }
+        if let Some(food) = self.food_at(coord) {
+            return Tile::for_food(food.kind);
+        }
+        for (player, snake) in self.snakes.iter().enumerate() {
+            if snake.head() == coord {
+                return Tile::head_for_player(player);
+            }
+            if snake.body.contains(coord) {
+                return Tile::for_player(player);
+            }
+        }
+        if self.is_portal(coord) {
+            return Tile::PORTAL;
+        }
+        if self.trail.iter().any(|(trail, _)| trail == coord) {
+            return Tile::TRAIL;
+        }
+        Tile::AIR
+    }
+
+    /// The tiles of the whole board, as rows from top to bottom, including the border walls. So the
+    /// tile of board coordinate (x, y) is at `[y + 1][x + 1]`.
+    pub fn render_grid(&self) -> Vec<Vec<Tile>> {
+        let (width, height) = self.coord_limits();
+        (-1..=height)
+            .map(|y| {
+                (-1..=width)
+                    .map(|x| self.tile_at(&Coord { x, y }))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The board as a string, one line per row including the border, the same as `Display` gives.
+    /// Without the terminal feature there are no themes, so it's plain text in the default glyphs.
+    #[cfg(not(feature = "terminal"))]
+    pub fn to_board_string(&self) -> String {
+        let mut board = String::new();
+        for row in self.render_grid() {
+            for tile in row {
+                board.push_str(tile.default_glyph());
+            }
+            board.push('\n');
+        }
+        board
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_board_string())
+    }
+}
+
+/// The inputs of a game and when they were given, from which the game can be played back exactly
+/// with `Game::replay`.
+///
+/// It's written as text by `Display` and read back by `FromStr`: a `<height> <width> <seed>
+/// <ticks>` line, followed by a `<tick> <key>` line for each input, where `tick` is the number of
+/// ticks done before the input was given and `key` is as in `Input::key`.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Replay {
+    pub height: u16,
+    pub width: u16,
+    pub seed: u64,
+    /// How many ticks the game lasted.
+    pub ticks: u64,
+    pub inputs: Vec<(u64, Input)>,
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} {} {} {}",
+            self.height, self.width, self.seed, self.ticks
+        )?;
+        for (tick, input) in &self.inputs {
+            writeln!(f, "{} {}", tick, input.key())?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Replay {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Replay, GameError> {
+        let mut lines = s.lines();
+        let header = lines.next().unwrap_or_default();
+        let invalid = |line: &str| GameError::InvalidReplay(line.to_string());
+
+        let fields: Vec<&str> = header.split(' ').collect();
+        let (height, width, seed, ticks) = match fields[..] {
+            [height, width, seed, ticks] => (
+                height.parse().map_err(|_| invalid(header))?,
+                width.parse().map_err(|_| invalid(header))?,
+                seed.parse().map_err(|_| invalid(header))?,
+                ticks.parse().map_err(|_| invalid(header))?,
+            ),
+            _ => return Err(invalid(header)),
+        };
+
+        let mut inputs = Vec::new();
+        for line in lines {
+            let input = line
+                .split_once(' ')
+                .and_then(|(tick, key)| Some((tick.parse().ok()?, Input::from_replay_key(key)?)));
+            inputs.push(input.ok_or_else(|| invalid(line))?);
+        }
+
+        Ok(Replay {
+            height,
+            width,
+            seed,
+            ticks,
+            inputs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turn_cooldown_holds_off_turns() {
+        let mut game = Game::builder(10, 10).turn_cooldown(5).build().unwrap();
+        game.step(Input::RIGHT);
+        assert_eq!(*game.heading(), Input::RIGHT);
+        assert!(!game.set_direction(Input::DOWN));
+        game.step(Input::DOWN);
+        assert_eq!(*game.heading(), Input::RIGHT);
+
+        // queued inputs wait until the cooldown is over, five ticks after the turn
+        game.queue_input(Input::DOWN);
+        for _ in 0..3 {
+            game.tick();
+            assert_eq!(*game.heading(), Input::RIGHT);
+        }
+        game.tick();
+        assert_eq!(*game.heading(), Input::DOWN);
+    }
+
+    fn coords(coords: &[(isize, isize)]) -> Vec<Coord> {
+        coords.iter().map(|&(x, y)| Coord { x, y }).collect()
+    }
+
+    #[test]
+    fn biting_into_the_body_cuts_it_off_there() {
+        let body = coords(&[
+            (1, 1),
+            (1, 2),
+            (2, 2),
+            (2, 1),
+            (2, 0),
+            (1, 0),
+            (0, 0),
+            (0, 1),
+            (0, 2),
+        ]);
+        let mut game = Game::builder(5, 5)
+            .seed(0)
+            .self_collision(SelfCollision::Bite)
+            .start(body, Input::UP)
+            .build()
+            .unwrap();
+        assert_eq!(game.step(Input::UP), GameState::RUNNING);
+        // the tail moves off of (0, 2), and then (1, 0) is bitten off along with what's behind it
+        let expected = coords(&[(1, 0), (1, 1), (1, 2), (2, 2), (2, 1), (2, 0)]);
+        assert_eq!(game.body().cloned().collect::<Vec<_>>(), expected);
+        assert_eq!(game.snake_len(), 6);
+    }
+
+    #[test]
+    fn the_bounds_of_a_straight_snake_are_its_ends() {
+        let game = Game::builder(5, 5)
+            .start(coords(&[(3, 2), (2, 2), (1, 2)]), Input::RIGHT)
+            .build()
+            .unwrap();
+        assert_eq!(
+            game.snake_bounds(),
+            (Coord { x: 1, y: 2 }, Coord { x: 3, y: 2 })
+        );
+    }
+
+    #[test]
+    fn the_bounds_of_a_coiled_snake_come_from_its_middle() {
+        // it doubles back around (2, 2), so neither the head nor the tail is on a corner
+        let body = coords(&[
+            (1, 2),
+            (1, 1),
+            (2, 1),
+            (3, 1),
+            (3, 2),
+            (3, 3),
+            (2, 3),
+            (2, 2),
+        ]);
+        let game = Game::builder(5, 5)
+            .start(body, Input::DOWN)
+            .build()
+            .unwrap();
+        assert_eq!(
+            game.snake_bounds(),
+            (Coord { x: 1, y: 1 }, Coord { x: 3, y: 3 })
+        );
+    }
+
+    #[test]
+    fn the_board_size_is_the_one_it_was_built_with() {
+        let game = Game::builder(4, 7).build().unwrap();
+        assert_eq!((game.height(), game.width()), (4, 7));
+        let game = Game::create_seeded(9, 5, 0).unwrap();
+        assert_eq!((game.height(), game.width()), (9, 5));
+    }
+
+    /// The tile the last of `updates` for `coord` draws, if there's one.
+    fn last_tile(updates: &[TermUpdate], coord: (isize, isize)) -> Option<Tile> {
+        let coord = Coord {
+            x: coord.0,
+            y: coord.1,
+        };
+        updates
+            .iter()
+            .rev()
+            .find(|update| *update.coord() == coord)
+            .map(TermUpdate::tile)
+    }
+
+    #[test]
+    fn biting_just_behind_the_neck_leaves_a_short_snake() {
+        let body = coords(&[(0, 0), (1, 0), (1, 1), (0, 1), (0, 2), (1, 2)]);
+        let mut game = Game::builder(5, 5)
+            .seed(0)
+            .self_collision(SelfCollision::Bite)
+            .start(body, Input::DOWN)
+            .build()
+            .unwrap();
+        let updates = game.tick();
+        assert_eq!(game.state, GameState::RUNNING);
+        // (0, 1) is the closest segment to the neck that the head can reach
+        let expected = coords(&[(0, 1), (0, 0), (1, 0), (1, 1)]);
+        assert_eq!(game.body().cloned().collect::<Vec<_>>(), expected);
+        assert_eq!(game.snake_len(), 4);
+        assert_eq!(last_tile(&updates, (0, 1)), Some(Tile::head_for_player(0)));
+        assert_eq!(last_tile(&updates, (0, 2)), Some(Tile::AIR));
+        assert_eq!(last_tile(&updates, (1, 2)), Some(Tile::AIR));
+    }
+
+    #[test]
+    fn biting_next_to_the_tail_cuts_off_one_segment() {
+        let body = coords(&[(1, 1), (2, 1), (2, 0), (1, 0), (0, 0), (0, 1), (0, 2)]);
+        let mut game = Game::builder(5, 5)
+            .seed(0)
+            .self_collision(SelfCollision::Bite)
+            .start(body, Input::LEFT)
+            .build()
+            .unwrap();
+        let updates = game.tick();
+        assert_eq!(game.state, GameState::RUNNING);
+        // the tail moves off of (0, 2), so the bitten (0, 1) next to it is all that comes off
+        let expected = coords(&[(0, 1), (1, 1), (2, 1), (2, 0), (1, 0), (0, 0)]);
+        assert_eq!(game.body().cloned().collect::<Vec<_>>(), expected);
+        assert_eq!(game.snake_len(), 6);
+        assert_eq!(last_tile(&updates, (0, 1)), Some(Tile::head_for_player(0)));
+        assert_eq!(last_tile(&updates, (0, 2)), Some(Tile::AIR));
+    }
+
+    #[test]
+    fn a_boxed_in_snake_has_no_legal_moves() {
+        // in the corner, with a wall to the right and its own body below
+        let game = Game::builder(5, 5)
+            .walls(coords(&[(1, 0)]).into_iter().collect())
+            .start(coords(&[(0, 0), (0, 1)]), Input::UP)
+            .build()
+            .unwrap();
+        assert_eq!(game.legal_moves(), Vec::new());
+    }
+
+    #[test]
+    fn wrapping_crosses_to_the_other_side() {
+        // 7 wide and 5 high, so that the axes can't be mixed up
+        for (start, input, expected) in [
+            ((0, 0), Input::UP, (0, 4)),
+            ((3, 4), Input::DOWN, (3, 0)),
+            ((0, 2), Input::LEFT, (6, 2)),
+            ((6, 2), Input::RIGHT, (0, 2)),
+        ] {
+            let mut game = Game::builder(5, 7)
+                .wall_mode(WallMode::Wrap)
+                .start(coords(&[start]), input)
+                .build()
+                .unwrap();
+            assert_eq!(game.step(input), GameState::RUNNING);
+            assert_eq!(*game.head(), coords(&[expected])[0]);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip_plays_on_the_same() {
+        let mut game = Game::builder(10, 10).seed(3).build().unwrap();
+        for _ in 0..10 {
+            game.step(ai::next_move(&game));
+        }
+        let json = serde_json::to_string(&game).unwrap();
+        let mut copy: Game = serde_json::from_str(&json).unwrap();
+        for _ in 0..30 {
+            let input = ai::next_move(&game);
+            assert_eq!(game.step(input), copy.step(input));
+            assert_eq!(game.encode(), copy.encode());
+        }
+    }
+
+    /// Whether any food is on a snake or a wall.
+    fn food_is_covered(game: &Game) -> bool {
+        game.food.iter().any(|food| {
+            game.walls.contains(&food.coord)
+                || game
+                    .snakes
+                    .iter()
+                    .any(|snake| snake.body.contains(&food.coord))
+        })
+    }
+
+    #[test]
+    fn food_is_never_placed_on_snakes_or_walls() {
+        for seed in 0..20 {
+            let mut game = Game::builder(8, 8)
+                .seed(seed)
+                .two_player()
+                .food_count(3)
+                .walls(
+                    coords(&[(3, 3), (3, 4), (4, 3), (4, 4)])
+                        .into_iter()
+                        .collect(),
+                )
+                .build()
+                .unwrap();
+            game.set_endless_respawn(true);
+            for _ in 0..500 {
+                if game.state != GameState::RUNNING {
+                    break;
+                }
+                game.step(ai::next_move(&game));
+                assert!(!food_is_covered(&game), "seed {}", seed);
+            }
+        }
+    }
+
+    #[test]
+    fn the_first_food_is_never_under_the_starting_snake() {
+        // the snake covers all of a 3 by 3 board but the last two cells
+        let body = coords(&[(0, 0), (1, 0), (2, 0), (2, 1), (1, 1), (0, 1), (0, 2)]);
+        for seed in 0..50 {
+            let game = Game::builder(3, 3)
+                .seed(seed)
+                .start(body.clone(), Input::DOWN)
+                .build()
+                .unwrap();
+            assert_eq!(game.food.len(), 1);
+            assert!(!food_is_covered(&game), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn only_a_snake_with_a_neck_cant_reverse() {
+        let mut game = Game::builder(5, 5)
+            .start(coords(&[(2, 2)]), Input::DOWN)
+            .build()
+            .unwrap();
+        assert!(game.is_legal_move(&Input::UP));
+        assert_eq!(game.step(Input::UP), GameState::RUNNING);
+        assert_eq!(*game.head(), coords(&[(2, 1)])[0]);
+
+        let mut game = Game::builder(5, 5)
+            .start(coords(&[(2, 2), (2, 1)]), Input::DOWN)
+            .build()
+            .unwrap();
+        assert!(!game.is_legal_move(&Input::UP));
+        assert_eq!(game.step(Input::UP), GameState::RUNNING);
+        assert_eq!(*game.head(), coords(&[(2, 3)])[0]);
+    }
+
+    #[test]
+    fn filling_a_2_by_2_board_wins() {
+        let game = Game::builder(2, 2)
+            .start(coords(&[(0, 0), (1, 0), (1, 1), (0, 1)]), Input::DOWN)
+            .build()
+            .unwrap();
+        assert_eq!(game.state, GameState::WON);
+        assert_eq!(game.food(), None);
+
+        let mut game = Game::builder(2, 2)
+            .start(coords(&[(0, 0), (1, 0), (1, 1)]), Input::DOWN)
+            .build()
+            .unwrap();
+        assert_eq!(game.food(), Some(&coords(&[(0, 1)])[0]));
+        assert_eq!(game.step(Input::DOWN), GameState::WON);
+        assert_eq!(game.snake_len(), 4);
+        assert_eq!(game.food(), None);
+    }
+
+    #[test]
+    fn coordinates_off_the_board_are_rejected() {
+        for (x, y) in [(5, 0), (0, 5), (-1, 0), (0, -1)] {
+            let start = Game::builder(5, 5)
+                .start(coords(&[(x, y)]), Input::DOWN)
+                .build();
+            assert_eq!(
+                start.err(),
+                Some(GameError::InvalidStart(format!(
+                    "segment ({}, {}) is out of bounds",
+                    x, y
+                )))
+            );
+            let walls = Game::builder(5, 5)
+                .walls(coords(&[(x, y)]).into_iter().collect())
+                .build();
+            assert_eq!(walls.err(), Some(GameError::InvalidWall(Coord { x, y })));
+        }
+    }
+
+    #[cfg(feature = "terminal")]
+    #[test]
+    fn updates_the_terminal_cant_address_fail_to_queue() {
+        let game = Game::create(5, 5).unwrap();
+        let update = TermUpdate {
+            type_: TermUpdateType::Food(FoodKind::Normal),
+            coord: Coord { x: -5, y: 0 },
+        };
+        let mut out = Vec::new();
+        assert!(update.queue(&mut out, &game).is_err());
+        assert!(out.is_empty());
+    }
+
+    #[cfg(feature = "terminal")]
+    #[test]
+    fn the_ascii_theme_displays_as_ascii() {
+        let mut game = Game::builder(6, 6)
+            .theme(Theme::ascii())
+            .two_player()
+            .shrink_food_chance(0.5)
+            .trail_length(3)
+            .walls(coords(&[(3, 3)]).into_iter().collect())
+            .portals(vec![(Coord { x: 1, y: 4 }, Coord { x: 4, y: 1 })])
+            .start(coords(&[(0, 2), (0, 1), (0, 0)]), Input::DOWN)
+            .build()
+            .unwrap();
+        game.step(Input::DOWN);
+        let board = game.to_string();
+        assert!(board.is_ascii(), "{}", board);
+    }
+
+    #[test]
+    fn seeded_games_play_out_the_same() {
+        let play = |seed| {
+            let mut game = Game::create_seeded(10, 10, seed).unwrap();
+            for input in [Input::RIGHT, Input::DOWN, Input::LEFT].repeat(3) {
+                game.step(input);
+            }
+            game
+        };
+        assert_eq!(play(7).encode(), play(7).encode());
+        // the seeds are part of the encoding, so compare where the food ended up instead
+        let foods = |game: Game| game.foods().cloned().collect::<Vec<_>>();
+        assert_ne!(foods(play(7)), foods(play(8)));
+    }
+
+    #[test]
+    fn ticks_update_the_cells_of_eaten_and_new_food() {
+        let mut game = Game::from_layout("#####\n#@  #\n#*  #\n#   #\n#####").unwrap();
+        let eaten = Coord { x: 0, y: 1 };
+        let updates = game.tick();
+        assert_eq!(game.score(), 1);
+        let new_food = game.food().unwrap().clone();
+        assert_ne!(new_food, eaten);
+        for coord in [eaten, new_food] {
+            assert!(
+                updates.iter().any(|update| *update.coord() == coord),
+                "{} isn't updated",
+                coord
+            );
+        }
+    }
+
+    #[cfg(feature = "terminal")]
+    #[test]
+    fn resetting_keeps_the_theme() {
+        use crossterm::style::Color;
+
+        let theme = Theme {
+            head: TileStyle::new("@", Some(Color::Rgb { r: 1, g: 2, b: 3 })),
+            ..Theme::default()
+        };
+        let mut game = Game::builder(5, 5).theme(theme).build().unwrap();
+        // as `InteractiveGame::run` does when the terminal has no truecolor
+        game.theme = game.theme.without_truecolor();
+        game.step(Input::RIGHT);
+        game.reset();
+        assert_eq!(game.tick_count(), 0);
+        assert_eq!(game.theme().head.color, Some(Color::AnsiValue(16)));
+    }
+
+    #[test]
+    fn shrinking_the_arena_keeps_the_food_topped_up() {
+        // the snake fills every cell but (0, 3), where the food has to be
+        let body = coords(&[
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (3, 0),
+            (3, 1),
+            (2, 1),
+            (1, 1),
+            (0, 1),
+            (0, 2),
+            (1, 2),
+            (2, 2),
+            (3, 2),
+            (3, 3),
+            (2, 3),
+            (1, 3),
+        ]);
+        let mut game = Game::builder(4, 4)
+            .shrinking_arena(1)
+            .start(body, Input::DOWN)
+            .build()
+            .unwrap();
+        game.set_endless_respawn(true);
+        assert_eq!(game.food(), Some(&Coord { x: 0, y: 3 }));
+        // the food has nowhere to go when the outer ring closes, but there's room for it once the
+        // snake caught on the ring respawns inside
+        assert_eq!(game.step(Input::DOWN), GameState::RUNNING);
+        assert_eq!(game.food.len(), 1);
+        assert!(!food_is_covered(&game));
+    }
+
+    #[test]
+    fn updates_keep_up_with_respawns_on_the_trail() {
+        // snakes caught by the closing rings respawn, sometimes onto their own trail
+        for seed in 0..300 {
+            let mut game = Game::builder(9, 9)
+                .seed(seed)
+                .shrinking_arena(7)
+                .trail_length(2)
+                .portals(vec![(Coord { x: 1, y: 4 }, Coord { x: 7, y: 4 })])
+                .build()
+                .unwrap();
+            game.set_endless_respawn(true);
+            let mut rng = ChaCha12Rng::seed_from_u64(seed);
+            let mut grid = game.render_grid();
+            while game.state == GameState::RUNNING {
+                if rng.gen_bool(0.3) {
+                    game.set_direction(INPUTS[rng.gen_range(0..INPUTS.len())]);
+                }
+                for update in game.tick() {
+                    let Coord { x, y } = *update.coord();
+                    grid[(y + 1) as usize][(x + 1) as usize] = update.tile();
+                }
+                assert_eq!(
+                    grid,
+                    game.render_grid(),
+                    "seed {}, tick {}",
+                    seed,
+                    game.tick_count()
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "terminal")]
+    #[test]
+    fn keys_are_read_through_the_key_bindings() {
+        let default = KeyBindings::default();
+        assert_eq!(Input::from_key("W", &default), Some(Input::UP));
+        assert_eq!(Input::from_key("left", &default), Some(Input::LEFT));
+        assert_eq!(Input::from_key("k", &default), None);
+
+        let vim = KeyBindings::vim();
+        assert_eq!(Input::from_key("k", &vim), Some(Input::UP));
+        assert_eq!(Input::from_key("h", &vim), Some(Input::LEFT));
+        assert_eq!(Input::from_key("w", &vim), None);
+    }
+
+    #[test]
+    fn recorded_games_replay_the_same() {
+        let mut game = Game::create_seeded(8, 8, 4).unwrap();
+        game.start_recording().unwrap();
+        while game.state == GameState::RUNNING {
+            game.queue_input(ai::next_move(&game));
+            game.tick();
+        }
+        let replay: Replay = game.recording().unwrap().to_string().parse().unwrap();
+        assert_eq!(Game::replay(&replay), Ok(game.state));
+    }
+
+    #[test]
+    fn games_that_wouldnt_replay_the_same_cant_be_recorded() {
+        let walled = Game::builder(8, 8)
+            .walls(coords(&[(3, 3)]).into_iter().collect())
+            .build();
+        let wrapping = Game::builder(8, 8).wall_mode(WallMode::Wrap).build();
+        let two_player = Game::builder(8, 8).two_player().build();
+        for (game, reason) in [
+            (walled, "a board of its own"),
+            (wrapping, "a wall mode"),
+            (two_player, "a board of its own"),
+        ] {
+            assert_eq!(
+                game.unwrap().start_recording(),
+                Err(GameError::NotReplayable(reason.to_string()))
+            );
+        }
+
+        let mut game = Game::create(8, 8).unwrap();
+        game.start_recording().unwrap();
+        game.set_endless_respawn(true);
+        assert!(game.recording().is_none());
+    }
+
+    #[test]
+    fn history_shares_the_initial_game() {
+        let mut game = Game::builder(8, 8).history(5).build().unwrap();
+        for _ in 0..10 {
+            game.step(ai::next_move(&game));
+        }
+        let initial = game.initial.as_ref().unwrap();
+        assert_eq!(game.history.len(), 5);
+        for snapshot in &game.history {
+            assert!(snapshot.history.is_empty());
+            assert!(Arc::ptr_eq(snapshot.initial.as_ref().unwrap(), initial));
+        }
+        // undoing gets the history back, and resetting still works afterwards
+        assert!(game.undo());
+        assert_eq!(game.history.len(), 4);
+        game.reset();
+        assert_eq!(game.tick_count(), 0);
+    }
 
-        ticker.join().unwrap();
-        input_handler.join().unwrap();
+    #[test]
+    fn bench_runs_fit_boards_over_255_cells_wide() {
+        let summary = bench_run(1, 200, 20, 300);
+        assert_eq!(summary.state, GameState::RUNNING);
+        assert_eq!(summary.ticks, 200);
+        assert_eq!(bench_run(1, 200, 20, 300), summary);
     }
 }
@@ -1,20 +1,446 @@
 use core::time;
-use snake::InteractiveGame;
+#[cfg(feature = "spectate")]
+use snake::spectate;
+use snake::{
+    ai, check_fits_terminal, high_scores, todays_seed, BorderStyle, Game, GameState,
+    InteractiveGame, KeyBindings, Mode, Theme,
+};
+use std::io::{stdout, Write};
+#[cfg(feature = "spectate")]
+use std::net::TcpListener;
 
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    style::{Print, Stylize},
+    terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType},
+    QueueableCommand,
+};
+
+const USAGE: &str = "\
+Usage: snake [options]
+
+Without --width, --height, --speed or --tick-ms, a menu asks for the size of the board and the
+speed.
+
+Options:
+    --width <cells>        width of the board (default: 20)
+    --height <cells>       height of the board (default: 10)
+    --speed <speed>        slow, normal or fast (default: normal)
+    --tick-ms <ms>         milliseconds between moves, instead of a --speed (default: 200)
+    --two-player           two snakes, on wasd and the arrow keys
+    --endless              nothing is deadly; walls just stop the snake
+    --hard                 eaten food leaves a wall behind
+    --shrink <ticks>       close the board in by a ring of walls every so many moves
+    --daily                today's board, the same for everyone, with one try
+    --border <style>       wall, thin or hidden (default: wall)
+    --vim-keys             steer with hjkl instead of wasd
+    --timer                show the time played below the board
+    --hud                  show the score and length below the board
+    --demo                 watch the snake play by itself";
+
+/// The options that need the `spectate` feature, which go after the others in the usage.
+#[cfg(feature = "spectate")]
+const SPECTATE_USAGE: &str = "
+    --host <address>       let others watch the game, e.g. on 0.0.0.0:7777
+    --watch <address>      watch a game hosted elsewhere instead of playing";
+
+/// How to run the program, as shown by `--help` and after invalid arguments.
+fn usage() -> String {
+    let mut usage = USAGE.to_string();
+    #[cfg(feature = "spectate")]
+    usage.push_str(SPECTATE_USAGE);
+    usage.push_str("\n    --help                 show this message");
+    usage
+}
+
+/// A board size to pick from in the menu.
+struct Size {
+    name: &'static str,
+    height: u16,
+    width: u16,
+}
+
+/// A speed to pick from in the menu.
+struct Speed {
+    name: &'static str,
+    tick_ms: u64,
+}
+
+// terminal cells are about twice as tall as they are wide, so these look roughly square
+const SIZES: [Size; 3] = [
+    Size {
+        name: "Small",
+        height: 8,
+        width: 16,
+    },
+    Size {
+        name: "Medium",
+        height: 10,
+        width: 20,
+    },
+    Size {
+        name: "Large",
+        height: 16,
+        width: 32,
+    },
+];
+
+const SPEEDS: [Speed; 3] = [
+    Speed {
+        name: "Slow",
+        tick_ms: 300,
+    },
+    Speed {
+        name: "Normal",
+        tick_ms: 200,
+    },
+    Speed {
+        name: "Fast",
+        tick_ms: 100,
+    },
+];
+
+/// The indices of what's picked when nothing is chosen.
+const DEFAULT_SIZE: usize = 1;
+const DEFAULT_SPEED: usize = 1;
+
+/// What was picked in the menu.
+struct MenuChoice {
+    height: u16,
+    width: u16,
+    tick_ms: u64,
+}
+
+struct Options {
+    height: Option<u16>,
+    width: Option<u16>,
+    tick_ms: Option<u64>,
+    speed: Option<u64>, // the milliseconds between moves of the picked speed
+    two_player: bool,
+    endless: bool,
+    hard: bool,
+    shrink: u64,
+    daily: bool,
+    border: BorderStyle,
+    vim_keys: bool,
+    timer: bool,
+    hud: bool,
+    demo: bool,
+    #[cfg(feature = "spectate")]
+    host: Option<String>,
+    #[cfg(feature = "spectate")]
+    watch: Option<String>,
+}
+
+/// Parse the command-line arguments, not including the program name.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        height: None,
+        width: None,
+        tick_ms: None,
+        speed: None,
+        two_player: false,
+        endless: false,
+        hard: false,
+        shrink: 0,
+        daily: false,
+        border: BorderStyle::Wall,
+        vim_keys: false,
+        timer: false,
+        hud: false,
+        demo: false,
+        #[cfg(feature = "spectate")]
+        host: None,
+        #[cfg(feature = "spectate")]
+        watch: None,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--width" => options.width = Some(parse_value(&arg, &value()?)?),
+            "--height" => options.height = Some(parse_value(&arg, &value()?)?),
+            "--speed" => {
+                let speed = value()?;
+                let speed = SPEEDS
+                    .iter()
+                    .find(|choice| choice.name.eq_ignore_ascii_case(&speed))
+                    .ok_or_else(|| format!("Invalid value {:?} for {}", speed, arg))?;
+                options.speed = Some(speed.tick_ms);
+            }
+            "--tick-ms" => match parse_value(&arg, &value()?)? {
+                0 => return Err("--tick-ms must be at least 1".to_string()),
+                tick_ms => options.tick_ms = Some(tick_ms),
+            },
+            "--two-player" => options.two_player = true,
+            "--endless" => options.endless = true,
+            "--hard" => options.hard = true,
+            "--shrink" => options.shrink = parse_value(&arg, &value()?)?,
+            "--daily" => options.daily = true,
+            "--border" => {
+                options.border = match value()?.as_str() {
+                    "wall" => BorderStyle::Wall,
+                    "thin" => BorderStyle::Thin,
+                    "hidden" => BorderStyle::Hidden,
+                    style => return Err(format!("Invalid value {:?} for {}", style, arg)),
+                }
+            }
+            "--vim-keys" => options.vim_keys = true,
+            "--timer" => options.timer = true,
+            "--hud" => options.hud = true,
+            "--demo" => options.demo = true,
+            #[cfg(feature = "spectate")]
+            "--host" => options.host = Some(value()?),
+            #[cfg(feature = "spectate")]
+            "--watch" => options.watch = Some(value()?),
+            _ => return Err(format!("Unknown argument {:?}", arg)),
+        }
+    }
+    if options.speed.is_some() && options.tick_ms.is_some() {
+        return Err("--speed and --tick-ms can't be combined".to_string());
+    }
+    if options.endless && options.hard {
+        return Err("--endless and --hard can't be combined".to_string());
+    }
+    Ok(options)
+}
+
+fn parse_value<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value {:?} for {}", value, arg))
+}
 
 fn main() {
-    // let mut game = Game::create(10, 40);
-    // while game.state == GameState::RUNNING {
-    //     print!("{}", game);
-    //     if let Some(input) = get_input() {
-    //         game.cur_input = input
-    //     }
-    //     game.tick();
-    // }
-    // println!("{:?}", game.state)
+    if std::env::args().any(|arg| arg == "--help") {
+        println!("{}", usage());
+        return;
+    }
+    let Options {
+        height,
+        width,
+        tick_ms,
+        speed,
+        two_player,
+        endless,
+        hard,
+        shrink,
+        daily,
+        border,
+        vim_keys,
+        timer,
+        hud,
+        demo,
+        #[cfg(feature = "spectate")]
+        host,
+        #[cfg(feature = "spectate")]
+        watch,
+    } = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, usage());
+            std::process::exit(2);
+        }
+    };
+    #[cfg(feature = "spectate")]
+    if let Some(address) = watch {
+        watch_game(&address);
+        return;
+    }
+    let tick_ms = tick_ms.or(speed);
+    let (height, width, tick_ms) = if height.is_none() && width.is_none() && tick_ms.is_none() {
+        match menu() {
+            Some(choice) => (choice.height, choice.width, choice.tick_ms),
+            None => return,
+        }
+    } else {
+        (
+            height.unwrap_or(SIZES[DEFAULT_SIZE].height),
+            width.unwrap_or(SIZES[DEFAULT_SIZE].width),
+            tick_ms.unwrap_or(SPEEDS[DEFAULT_SPEED].tick_ms),
+        )
+    };
+    let tick_wait = time::Duration::from_millis(tick_ms);
+    let countdown_step = time::Duration::from_millis(700);
+
+    let theme = Theme {
+        border,
+        ..Theme::default()
+    };
+    let fits = check_fits_terminal(height, width, &theme);
+    let mut builder = Game::builder(height, width).theme(theme);
+    if two_player {
+        builder = builder.two_player();
+    }
+    if endless {
+        builder = builder.mode(Mode::Endless);
+    }
+    if hard {
+        builder = builder.mode(Mode::Hard);
+    }
+    builder = builder.shrinking_arena(shrink);
+    if daily {
+        builder = builder.seed(todays_seed());
+    }
+    let game = match fits.and_then(|()| builder.build()) {
+        Ok(game) => game,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    let mut interactive_game = InteractiveGame::new(game, tick_wait);
+    if vim_keys {
+        interactive_game = interactive_game.with_key_bindings(KeyBindings::vim());
+    }
+    if timer {
+        interactive_game = interactive_game.with_timer();
+    }
+    if hud {
+        interactive_game = interactive_game.with_hud();
+    }
+    #[cfg(feature = "spectate")]
+    if let Some(address) = host {
+        match TcpListener::bind(&address) {
+            Ok(listener) => interactive_game = interactive_game.with_spectators(listener),
+            Err(err) => {
+                eprintln!("Couldn't listen on {}: {}", address, err);
+                std::process::exit(1);
+            }
+        }
+    }
+    let interactive_game = if demo {
+        interactive_game.with_controller(ai::next_move)
+    } else if daily {
+        // a restart would get a new seed, and there's one try a day anyway
+        interactive_game.with_countdown(countdown_step)
+    } else {
+        interactive_game
+            .with_countdown(countdown_step)
+            .with_restart()
+    };
 
     enable_raw_mode().unwrap();
-    InteractiveGame::play(10, 10, time::Duration::from_millis(200));
+    let summary = interactive_game.run();
     disable_raw_mode().unwrap();
+    if two_player || demo {
+        return;
+    }
+
+    let score = summary.score;
+    println!("Score: {}", score);
+    // a game that was given up on doesn't count
+    if summary.state == GameState::QUIT {
+        return;
+    }
+    if let Some(path) = high_scores::default_path() {
+        let name = std::env::var("USER").unwrap_or_else(|_| "player".to_string());
+        match high_scores::record_score(&path, &name, score) {
+            Ok(true) => println!("New high score!"),
+            Ok(false) => {}
+            Err(err) => eprintln!("Couldn't record the score in {}: {}", path.display(), err),
+        }
+    }
+}
+
+/// Ask for the size of the board and the speed, with the arrow keys to pick and Enter to start.
+/// Returns `None` if Esc, `q` or Ctrl-C was pressed instead.
+fn menu() -> Option<MenuChoice> {
+    enable_raw_mode().unwrap();
+    let mut stdout = stdout();
+    stdout.queue(Hide).unwrap();
+    // the row being changed, and the picked item on each row
+    let mut row = 0;
+    let mut picked = [DEFAULT_SIZE, DEFAULT_SPEED];
+    let choice = loop {
+        draw_menu(&mut stdout, row, picked).unwrap();
+        let key = match event::read().unwrap() {
+            Event::Key(key) => key,
+            _ => continue,
+        };
+        let items = [SIZES.len(), SPEEDS.len()][row];
+        match key {
+            KeyEvent {
+                kind: KeyEventKind::Release,
+                ..
+            } => {}
+            KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => break None,
+            KeyEvent { code, .. } => match code {
+                KeyCode::Esc | KeyCode::Char('q') => break None,
+                KeyCode::Enter => break Some(picked),
+                KeyCode::Up => row = row.saturating_sub(1),
+                KeyCode::Down => row = (row + 1).min(picked.len() - 1),
+                KeyCode::Left => picked[row] = picked[row].saturating_sub(1),
+                KeyCode::Right => picked[row] = (picked[row] + 1).min(items - 1),
+                _ => {}
+            },
+        }
+    };
+    stdout
+        .queue(Clear(ClearType::All))
+        .and_then(|stdout| stdout.queue(MoveTo(0, 0)))
+        .and_then(|stdout| stdout.queue(Show))
+        .and_then(|stdout| stdout.flush())
+        .unwrap();
+    disable_raw_mode().unwrap();
+    choice.map(|[size, speed]| MenuChoice {
+        height: SIZES[size].height,
+        width: SIZES[size].width,
+        tick_ms: SPEEDS[speed].tick_ms,
+    })
+}
+
+fn draw_menu(stdout: &mut impl Write, row: usize, picked: [usize; 2]) -> crossterm::Result<()> {
+    stdout
+        .queue(Clear(ClearType::All))?
+        .queue(MoveTo(0, 0))?
+        .queue(Print("Snake\r\n\r\n"))?;
+    let rows = [
+        ("Size", SIZES[picked[0]].name),
+        ("Speed", SPEEDS[picked[1]].name),
+    ];
+    for (index, (label, name)) in rows.into_iter().enumerate() {
+        let line = format!("{:<6} < {:<6} >", label, name);
+        if index == row {
+            stdout.queue(Print(line.reverse()))?;
+        } else {
+            stdout.queue(Print(line))?;
+        }
+        stdout.queue(Print("\r\n"))?;
+    }
+    stdout.queue(Print(
+        "\r\nArrow keys to choose, Enter to start, Esc to quit",
+    ))?;
+    stdout.flush()
+}
+
+/// Show the game hosted at `address` until the host goes away.
+#[cfg(feature = "spectate")]
+fn watch_game(address: &str) {
+    let states = match spectate::spectate(address) {
+        Ok(states) => states,
+        Err(err) => {
+            eprintln!("Couldn't connect to {}: {}", address, err);
+            std::process::exit(1);
+        }
+    };
+    let mut stdout = stdout();
+    let mut received = false;
+    for board in states {
+        stdout
+            .queue(Clear(ClearType::All))
+            .and_then(|stdout| stdout.queue(MoveTo(0, 0)))
+            .and_then(|stdout| stdout.queue(Print(&board)))
+            .and_then(|stdout| stdout.flush())
+            .unwrap();
+        received = true;
+    }
+    if !received {
+        eprintln!("Nothing was received from {}", address);
+        std::process::exit(1);
+    }
 }
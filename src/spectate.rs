@@ -0,0 +1,276 @@
+//! Watching a game over the network. A game run with `InteractiveGame::with_spectators` sends every
+//! spectator that connects the whole board, and then what changes on it every tick.
+//!
+//! Every message is a frame: its length as a varint, encoded like the numbers of `Game::encode`,
+//! and then that many bytes. The first of those is the kind of the frame:
+//!
+//! - 0, a board: the rest is the whole game, as encoded by `Game::encode`. This is the first frame
+//!   a spectator gets, and it's sent again whenever the board is redrawn from scratch, e.g. when
+//!   the game is restarted.
+//! - 1, updates: the state of the game, as in `Game::encode`, and then a list of the cells that
+//!   changed. Each is the kind of change (0 for cleared, 1 for a snake segment, 2 for a snake head,
+//!   3 for food, 4 for a trail, 5 for a wall and 6 for a portal), then the player for snakes or the
+//!   kind of food for food, and then the coordinate.
+
+use crate::encoding::{food_kind_code, invalid, push_coord, push_varint, state_code, Decoder};
+use crate::{Game, GameError, GameState, TermUpdate, TermUpdateType, Tile};
+use std::fmt;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{self, SyncSender, TrySendError},
+    Arc, Mutex,
+};
+use std::thread::{self, JoinHandle};
+use std::time;
+
+/// How many frames a spectator can fall behind by before it's disconnected, so that a slow one
+/// never holds up the game.
+const SPECTATOR_QUEUE_CAPACITY: usize = 64;
+
+/// The longest frame a spectator accepts, to not run out of memory over a bogus length.
+const MAX_FRAME_LEN: usize = 1 << 24;
+
+/// The widest and tallest board a spectator accepts, for the same reason. That's far more than
+/// fits in any terminal.
+const MAX_BOARD_SIZE: u16 = 1024;
+
+const BOARD_FRAME: u8 = 0;
+const UPDATES_FRAME: u8 = 1;
+
+/// What a spectator sees of a game.
+#[derive(Clone, Debug)]
+pub struct BoardState {
+    /// The tiles of the board, including the border, as rows from top to bottom like
+    /// `Game::render_grid` gives them.
+    pub tiles: Vec<Vec<Tile>>,
+    pub state: GameState,
+}
+
+impl fmt::Display for BoardState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in &self.tiles {
+            for tile in row {
+                write!(f, "{}", tile)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl BoardState {
+    /// Apply a frame sent by the host. An updates frame needs a board to apply to.
+    fn apply(state: Option<BoardState>, frame: &[u8]) -> Result<BoardState, GameError> {
+        match frame.split_first() {
+            Some((&BOARD_FRAME, encoded)) => {
+                let game = Game::decode(encoded)?;
+                if game.width() > MAX_BOARD_SIZE || game.height() > MAX_BOARD_SIZE {
+                    return Err(invalid(format!(
+                        "a {}x{} board is too big to watch",
+                        game.width(),
+                        game.height()
+                    )));
+                }
+                Ok(BoardState {
+                    tiles: game.render_grid(),
+                    state: game.state,
+                })
+            }
+            Some((&UPDATES_FRAME, updates)) => {
+                let mut state =
+                    state.ok_or_else(|| invalid("updates came before the board".to_string()))?;
+                let mut decoder = Decoder::new(updates);
+                state.state = decoder.state()?;
+                for _ in 0..decoder.number::<usize>()? {
+                    let type_ = match decoder.varint()? {
+                        0 => TermUpdateType::Clear,
+                        1 => TermUpdateType::Snake(decoder.number()?),
+                        2 => TermUpdateType::SnakeHead(decoder.number()?),
+                        3 => TermUpdateType::Food(decoder.food_kind()?),
+                        4 => TermUpdateType::Trail,
+                        5 => TermUpdateType::Wall,
+                        6 => TermUpdateType::Portal,
+                        kind => return Err(invalid(format!("invalid update kind {}", kind))),
+                    };
+                    let coord = decoder.any_coord()?;
+                    let tile = TermUpdate {
+                        type_,
+                        coord: coord.clone(),
+                    }
+                    .tile();
+                    let cell = usize::try_from(coord.y + 1)
+                        .ok()
+                        .zip(usize::try_from(coord.x + 1).ok())
+                        .and_then(|(row, column)| state.tiles.get_mut(row)?.get_mut(column));
+                    match cell {
+                        Some(cell) => *cell = tile,
+                        None => return Err(invalid(format!("{} is out of bounds", coord))),
+                    }
+                }
+                decoder.finish()?;
+                Ok(state)
+            }
+            _ => Err(invalid("unknown frame".to_string())),
+        }
+    }
+}
+
+/// Watch the game hosted at `addr`. The iterator gives what the board looks like after each frame
+/// the host sends, and ends when the connection is closed or something invalid is received.
+pub fn spectate(addr: impl ToSocketAddrs) -> io::Result<impl Iterator<Item = BoardState>> {
+    let mut reader = BufReader::new(TcpStream::connect(addr)?);
+    let mut state = None;
+    Ok(std::iter::from_fn(move || {
+        let frame = read_frame(&mut reader).ok()?;
+        let next = BoardState::apply(state.take(), &frame).ok()?;
+        state = Some(next.clone());
+        Some(next)
+    }))
+}
+
+fn read_frame(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len: usize = 0;
+    for shift in (0..usize::BITS).step_by(7) {
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        len |= usize::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(ErrorKind::InvalidData, "frame too long"));
+    }
+    let mut frame = vec![0; len];
+    reader.read_exact(&mut frame)?;
+    Ok(frame)
+}
+
+fn framed(payload: Vec<u8>) -> Arc<Vec<u8>> {
+    let mut frame = Vec::with_capacity(payload.len() + 4);
+    push_varint(&mut frame, payload.len() as u128);
+    frame.extend(payload);
+    Arc::new(frame)
+}
+
+/// The frame with the whole of `game`.
+pub(crate) fn board_frame(game: &Game) -> Arc<Vec<u8>> {
+    let mut payload = vec![BOARD_FRAME];
+    payload.extend(game.encode());
+    framed(payload)
+}
+
+/// The frame with what a tick changed, and the state of the game after it.
+pub(crate) fn updates_frame(state: GameState, term_updates: &[TermUpdate]) -> Arc<Vec<u8>> {
+    let mut payload = vec![UPDATES_FRAME];
+    push_varint(&mut payload, state_code(state));
+    push_varint(&mut payload, term_updates.len() as u128);
+    for term_update in term_updates {
+        match term_update.type_ {
+            TermUpdateType::Clear => push_varint(&mut payload, 0),
+            TermUpdateType::Snake(player) => {
+                push_varint(&mut payload, 1);
+                push_varint(&mut payload, player as u128);
+            }
+            TermUpdateType::SnakeHead(player) => {
+                push_varint(&mut payload, 2);
+                push_varint(&mut payload, player as u128);
+            }
+            TermUpdateType::Food(kind) => {
+                push_varint(&mut payload, 3);
+                push_varint(&mut payload, food_kind_code(kind));
+            }
+            TermUpdateType::Trail => push_varint(&mut payload, 4),
+            TermUpdateType::Wall => push_varint(&mut payload, 5),
+            TermUpdateType::Portal => push_varint(&mut payload, 6),
+        }
+        push_coord(&mut payload, &term_update.coord);
+    }
+    framed(payload)
+}
+
+/// The spectators of a game, each with a queue of frames that a thread of its own sends.
+#[derive(Default)]
+pub(crate) struct Spectators {
+    queues: Mutex<Vec<SyncSender<Arc<Vec<u8>>>>>,
+}
+
+impl Spectators {
+    /// Start sending frames to `stream`, beginning with `first`.
+    fn add(&self, stream: TcpStream, first: Arc<Vec<u8>>) {
+        let (sender, receiver) = mpsc::sync_channel::<Arc<Vec<u8>>>(SPECTATOR_QUEUE_CAPACITY);
+        // the queue is empty, so this can't fail
+        let _ = sender.try_send(first);
+        self.queues.lock().unwrap().push(sender);
+        thread::spawn(move || {
+            let mut out = BufWriter::new(stream);
+            for frame in receiver {
+                if out.write_all(&frame).and_then(|()| out.flush()).is_err() {
+                    // disconnected
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Queue `frame` for every spectator, disconnecting the ones that have fallen too far behind or
+    /// are gone. This never blocks.
+    pub(crate) fn send(&self, frame: Arc<Vec<u8>>) {
+        self.queues
+            .lock()
+            .unwrap()
+            .retain(|queue| match queue.try_send(Arc::clone(&frame)) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => false,
+            });
+    }
+}
+
+/// Accept spectators on `listener` until `stop` is set, checking for it every `poll_interval`.
+/// Each new spectator is sent the board as it is in `game_mut` and then joins `spectators`, both
+/// under the game's lock so that it doesn't miss any update.
+pub(crate) fn spawn_acceptor(
+    listener: TcpListener,
+    game_mut: Arc<Mutex<Game>>,
+    spectators: Arc<Spectators>,
+    stop: Arc<AtomicBool>,
+    poll_interval: time::Duration,
+) -> io::Result<JoinHandle<()>> {
+    // non-blocking so that stop is noticed even if nobody connects
+    listener.set_nonblocking(true)?;
+    Ok(thread::spawn(move || {
+        while !stop.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if stream.set_nonblocking(false).is_err() {
+                        continue;
+                    }
+                    let game = game_mut.lock().unwrap();
+                    spectators.add(stream, board_frame(&game));
+                }
+                // nobody is connecting, or e.g. the spectator hung up before being accepted
+                Err(_) => thread::sleep(poll_interval),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boards_too_big_to_watch_are_rejected() {
+        let game = Game::builder(3, MAX_BOARD_SIZE + 1).build().unwrap();
+        let mut frame = vec![BOARD_FRAME];
+        frame.extend(game.encode());
+        assert!(BoardState::apply(None, &frame).is_err());
+
+        let game = Game::builder(3, MAX_BOARD_SIZE).build().unwrap();
+        let mut frame = vec![BOARD_FRAME];
+        frame.extend(game.encode());
+        assert!(BoardState::apply(None, &frame).is_ok());
+    }
+}
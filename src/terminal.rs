@@ -0,0 +1,1170 @@
+//! Drawing games in the terminal, and playing them there interactively. This needs the `terminal`
+//! feature, and it's the only part of the crate that uses threads.
+
+#[cfg(feature = "spectate")]
+use crate::spectate::{self, Spectators};
+use crate::theme::colors_enabled;
+use crate::{
+    supports_truecolor, todays_seed, Coord, DeathCause, Game, GameError, GameState, GameSummary,
+    Input, KeyBindings, Replay, TermUpdate, TermUpdateType, Theme, Tile,
+};
+use core::time;
+use crossterm::{
+    cursor::{self, Hide, Show},
+    event::{
+        poll, read, DisableFocusChange, EnableFocusChange, Event,
+        KeyCode::{Char, Esc},
+        KeyEvent, KeyEventKind, KeyModifiers,
+    },
+    style::{Print, StyledContent, Stylize},
+    terminal::{self, Clear, ClearType},
+    QueueableCommand,
+};
+use std::io::{self, stdout, BufWriter, Write};
+#[cfg(feature = "spectate")]
+use std::net::TcpListener;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::Sender,
+    Arc, Mutex,
+};
+use std::thread;
+use std::time::Instant;
+
+/// How often `InteractiveGame` checks for input by default, independently of the tick rate.
+const DEFAULT_POLL_INTERVAL: time::Duration = time::Duration::from_millis(16);
+
+impl TermUpdate {
+    /// Queue this update of `game` on `out`, drawn with the game's theme. Fails without queueing
+    /// anything if the coordinate is outside of what the terminal can address.
+    ///
+    /// With a `Theme::gradient`, each segment's color depends on how far along the body it is,
+    /// which changes for the whole snake as it moves. So after a tick's updates, queue
+    /// `Game::redraw_snakes` as well.
+    pub fn queue(&self, out: &mut impl Write, game: &Game) -> crossterm::Result<()> {
+        let tile = match self.type_ {
+            TermUpdateType::Snake(player) => game.render_segment(&game.theme, player, &self.coord),
+            _ => self.tile().render_with(&game.theme),
+        };
+        queue_cell(out, game, &self.coord, tile)
+    }
+}
+
+/// Queue `tile` to be printed at the board coordinate `coord`, offsetting by the width of the
+/// border that `theme` draws: (+1, +1) for most, with the border itself at x/y -1 and width/height,
+/// and (0, 0) when it's hidden. Coordinates that the terminal can't address are an `InvalidInput`
+/// error, and nothing is queued for them.
+fn queue_tile(
+    out: &mut impl Write,
+    theme: &Theme,
+    coord: &Coord,
+    tile: StyledContent<&str>,
+) -> crossterm::Result<()> {
+    let column = u16::try_from(coord.x + theme.border_width());
+    let row = u16::try_from(coord.y + theme.border_width());
+    match (column, row) {
+        (Ok(column), Ok(row)) => {
+            out.queue(cursor::MoveTo(column, row))?.queue(Print(tile))?;
+            Ok(())
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} can't be drawn in the terminal", coord),
+        )),
+    }
+}
+
+/// Queue `tile` like `queue_tile` does, at the cell of the board of `game` at `coord`, which is
+/// first flipped if the game's theme says so.
+fn queue_cell(
+    out: &mut impl Write,
+    game: &Game,
+    coord: &Coord,
+    tile: StyledContent<&str>,
+) -> crossterm::Result<()> {
+    let (_, height) = game.coord_limits();
+    queue_tile(out, &game.theme, &game.theme.oriented(coord, height), tile)
+}
+
+impl Game {
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// The board as a string, one line per row including the border, the same as `Display` gives.
+    /// It's drawn with the game's theme, without colors if `set_color_choice` says so.
+    pub fn to_board_string(&self) -> String {
+        if colors_enabled() {
+            self.to_board_string_themed(&self.theme)
+        } else {
+            self.to_board_string_themed(&self.theme.without_colors())
+        }
+    }
+
+    /// Like `to_board_string`, but drawn with `theme` instead of the game's own, border style
+    /// included. A theme without any colors gives plain text, without escape codes.
+    pub fn to_board_string_themed(&self, theme: &Theme) -> String {
+        let limits = self.coord_limits();
+        let (width, height) = limits;
+        let border = theme.border_width();
+        let mut board = String::new();
+        for y in -border..height + border {
+            for x in -border..width + border {
+                // the border looks the same either way up, so only the board itself is flipped
+                let coord = Coord { x, y };
+                let styled = if self.coord_is_in_bounds(&coord) {
+                    self.render_cell(theme, &theme.oriented(&coord, height))
+                } else {
+                    theme.render_border(&coord, limits)
+                };
+                board.push_str(&styled.to_string());
+            }
+            board.push('\n');
+        }
+        board
+    }
+
+    /// How the cell at `coord` is drawn with `theme`, which is its `tile_at`, with the snakes'
+    /// bodies faded by the theme's gradient.
+    fn render_cell<'a>(&self, theme: &'a Theme, coord: &Coord) -> StyledContent<&'a str> {
+        let tile = self.tile_at(coord);
+        if let Tile::SNAKE | Tile::RIVAL = tile {
+            if let Some(player) = self
+                .snakes
+                .iter()
+                .position(|snake| snake.body.contains(coord))
+            {
+                return self.render_segment(theme, player, coord);
+            }
+        }
+        tile.render_with(theme)
+    }
+
+    /// Segment `coord` of `player`'s snake, other than the head, drawn with `theme`.
+    fn render_segment<'a>(
+        &self,
+        theme: &'a Theme,
+        player: usize,
+        coord: &Coord,
+    ) -> StyledContent<&'a str> {
+        let body = &self.snakes[player].body;
+        // a segment that has just been left is about to be drawn over anyway
+        let index = body
+            .iter()
+            .position(|segment| segment == coord)
+            .unwrap_or(1);
+        theme.render_segment(player, index, body.len())
+    }
+
+    /// Queue every snake on `out`, in place. Nothing is flushed. With a `Theme::gradient`, this is
+    /// needed after every tick's `TermUpdate`s, since the color of every segment changes as the
+    /// snake moves.
+    pub fn redraw_snakes(&self, out: &mut impl Write) -> crossterm::Result<()> {
+        let theme = &self.theme;
+        for (player, snake) in self.snakes.iter().enumerate() {
+            queue_cell(
+                out,
+                self,
+                snake.head(),
+                Tile::head_for_player(player).render_with(theme),
+            )?;
+            for (index, coord) in snake.body.iter().enumerate().skip(1) {
+                let segment = theme.render_segment(player, index, snake.body.len());
+                queue_cell(out, self, coord, segment)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Queue the initial board on `out`, clearing the screen first. Nothing is flushed. This is the
+    /// same as `redraw_all`.
+    pub fn draw_initial(&self, out: &mut impl Write) -> crossterm::Result<()> {
+        self.redraw_all(out)
+    }
+
+    /// Queue the whole board on `out`, border included, clearing the screen first. Nothing is
+    /// flushed. This is meant for the first frame and for repairing the screen, e.g. after a
+    /// resize; between ticks, queueing the tick's `TermUpdate`s only redraws the cells that
+    /// changed.
+    pub fn redraw_all(&self, out: &mut impl Write) -> crossterm::Result<()> {
+        out.queue(Clear(ClearType::All))?;
+
+        // draw the border, if it isn't hidden, and the walls. The border looks the same either way
+        // up, so it's never flipped
+        let theme = &self.theme;
+        let limits = self.coord_limits();
+        let (width, height) = limits;
+        let border = theme.border_width();
+        for y in -border..height + border {
+            for x in -border..width + border {
+                let coord = Coord { x, y };
+                if !self.coord_is_in_bounds(&coord) {
+                    queue_tile(out, theme, &coord, theme.render_border(&coord, limits))?;
+                }
+            }
+        }
+        for wall in &self.walls {
+            queue_cell(out, self, wall, Tile::WALL.render_with(theme))?;
+        }
+
+        // draw the portals, which the snakes are drawn over
+        for end in self.portals.iter().flat_map(|(a, b)| [a, b]) {
+            queue_cell(out, self, end, Tile::PORTAL.render_with(theme))?;
+        }
+
+        self.redraw_snakes(out)?;
+
+        // draw the trail, which nothing else overlaps
+        for (coord, _) in &self.trail {
+            queue_cell(out, self, coord, Tile::TRAIL.render_with(theme))?;
+        }
+
+        // draw the food
+        for food in &self.food {
+            queue_cell(
+                out,
+                self,
+                &food.coord,
+                Tile::for_food(food.kind).render_with(theme),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Something that happened during interactive play, for observers such as overlays, bots or
+/// loggers.
+///
+/// Every tick sends `Tick` first, then `AteFood` for each player that ate during it, then `Died` or
+/// `Won` if it ended the game. Nothing is sent for ticks skipped while paused.
+#[derive(Clone, PartialEq, Debug)]
+pub enum GameEvent {
+    Tick {
+        /// How long it's been since the previous time the game could have ticked, as measured,
+        /// which is about the tick wait unless the game falls behind.
+        interval: time::Duration,
+    },
+    AteFood {
+        player: usize,
+        at: Coord,
+        score: usize,
+    },
+    Died {
+        cause: DeathCause,
+    },
+    Won,
+}
+
+/// Send the events for the tick that `game` just did, `interval` after the previous one could have.
+/// `prev_scores` are the scores of each player before that tick.
+fn send_tick_events(
+    events: &Sender<GameEvent>,
+    game: &Game,
+    prev_scores: &[usize],
+    interval: time::Duration,
+) {
+    // sending only fails if the receiver is gone, which is no reason to stop playing
+    let _ = events.send(GameEvent::Tick { interval });
+    for (player, snake) in game.snakes.iter().enumerate() {
+        if snake.food_eaten > prev_scores[player] {
+            let _ = events.send(GameEvent::AteFood {
+                player,
+                at: snake.head().clone(),
+                score: snake.food_eaten,
+            });
+        }
+    }
+    match game.state {
+        GameState::DEAD => {
+            if let Some(cause) = game.death_cause() {
+                let _ = events.send(GameEvent::Died { cause });
+            }
+        }
+        GameState::WON => {
+            let _ = events.send(GameEvent::Won);
+        }
+        _ => {}
+    }
+}
+
+/// Redraw the whole of `game` on a terminal of the given size, or if it doesn't fit, clear the
+/// screen and ask for a bigger terminal instead. Returns whether the board fit. Nothing is flushed.
+fn redraw(game: &Game, out: &mut impl Write, terminal_size: (u16, u16)) -> crossterm::Result<bool> {
+    let border = game.theme.border_width();
+    if fits_terminal(game.height, game.width, border, terminal_size) {
+        game.redraw_all(out)?;
+        return Ok(true);
+    }
+    let (needed_columns, needed_rows) = needed_terminal_size(game.height, game.width, border);
+    out.queue(Clear(ClearType::All))?
+        .queue(cursor::MoveTo(0, 0))?
+        .queue(Print(format!(
+            "Make the terminal at least {}x{} to keep playing",
+            needed_columns, needed_rows
+        )))?;
+    Ok(false)
+}
+
+/// The terminal size, as (columns, rows), that a board of the given size needs for itself and a
+/// border `border` cells wide.
+fn needed_terminal_size(height: u16, width: u16, border: isize) -> (u32, u32) {
+    let border = 2 * border as u32;
+    (u32::from(width) + border, u32::from(height) + border)
+}
+
+/// Whether a board of the given size, with a border `border` cells wide, fits in a terminal of
+/// `(columns, rows)`. Some terminals report a size of zero when they don't know it, so that's
+/// assumed to fit.
+fn fits_terminal(height: u16, width: u16, border: isize, (columns, rows): (u16, u16)) -> bool {
+    let (needed_columns, needed_rows) = needed_terminal_size(height, width, border);
+    (columns, rows) == (0, 0)
+        || (u32::from(columns) >= needed_columns && u32::from(rows) >= needed_rows)
+}
+
+/// Check that a board of the given size, along with the border `theme` draws, fits in the current
+/// terminal. If the size of the terminal can't be determined, the board is assumed to fit.
+pub fn check_fits_terminal(height: u16, width: u16, theme: &Theme) -> Result<(), GameError> {
+    let (columns, rows) = match terminal::size() {
+        Ok(terminal_size) => terminal_size,
+        Err(_) => return Ok(()),
+    };
+    if fits_terminal(height, width, theme.border_width(), (columns, rows)) {
+        Ok(())
+    } else {
+        Err(GameError::BoardTooLargeForTerminal {
+            height,
+            width,
+            columns,
+            rows,
+        })
+    }
+}
+
+/// Queue `lines` over the middle of the board of `game`, each centered on its own. Lines that don't
+/// fit on the board start at the left edge of the terminal instead. Nothing is flushed.
+fn queue_centered(game: &Game, out: &mut impl Write, lines: &[&str]) -> crossterm::Result<()> {
+    let (width, height) = game.coord_limits();
+    let border = game.theme.border_width();
+    let top = ((height - lines.len() as isize) / 2).max(-border);
+    for (index, line) in lines.iter().enumerate() {
+        let coord = Coord {
+            x: ((width - line.chars().count() as isize) / 2).max(-border),
+            y: top + index as isize,
+        };
+        queue_tile(out, &game.theme, &coord, line.stylize())?;
+    }
+    Ok(())
+}
+
+/// The line shown below the board: the first player's score and length if `hud` is enabled, and
+/// the time played if there's an `elapsed` time. It's empty if neither is.
+fn status_line(game: &Game, hud: bool, elapsed: Option<time::Duration>) -> String {
+    let mut parts = Vec::new();
+    if hud {
+        parts.push(format!("Score: {}", game.score()));
+        parts.push(format!("Length: {}", game.snake_len()));
+    }
+    if let Some(elapsed) = elapsed {
+        parts.push(format!("Time: {:.1}s", elapsed.as_secs_f64()));
+    }
+    parts.join("   ")
+}
+
+/// Queue `status` on the line below the board, replacing whatever was there, if the terminal has
+/// room for it. It may not, as only the board itself is required to fit. Nothing is flushed.
+fn queue_status(game: &Game, out: &mut impl Write, status: &str) -> crossterm::Result<()> {
+    // if the size can't be determined, assume the line fits
+    let terminal_size = terminal::size().unwrap_or((u16::MAX, u16::MAX));
+    let border = game.theme.border_width();
+    let fits = terminal_size == (0, 0)
+        || (fits_terminal(
+            game.height.saturating_add(1),
+            game.width,
+            border,
+            terminal_size,
+        ) && status.chars().count() <= usize::from(terminal_size.0));
+    if !fits {
+        return Ok(());
+    }
+    let (_, height) = game.coord_limits();
+    queue_tile(
+        out,
+        &game.theme,
+        &Coord {
+            x: -border,
+            y: height + border,
+        },
+        status.stylize(),
+    )?;
+    out.queue(Clear(ClearType::UntilNewLine))?;
+    Ok(())
+}
+
+/// Queue `status` like `queue_status` does, but only if it differs from what's `shown`, to avoid
+/// flicker. `shown` is updated to match. Nothing is flushed.
+fn update_status(
+    game: &Game,
+    out: &mut impl Write,
+    shown: &Mutex<String>,
+    status: String,
+) -> crossterm::Result<()> {
+    let mut shown = shown.lock().unwrap();
+    if status != *shown {
+        queue_status(game, out, &status)?;
+        *shown = status;
+    }
+    Ok(())
+}
+
+/// The lines of the debug overlay: where the first player's snake's head is, which way it's
+/// heading, how many ticks have been done and how long the snake is.
+fn debug_lines(game: &Game) -> Vec<String> {
+    vec![
+        format!("Head: {}", game.head()),
+        format!("Heading: {:?}", game.heading()),
+        format!("Tick: {}", game.tick_count()),
+        format!("Length: {}", game.snake_len()),
+    ]
+}
+
+/// Queue line `index` of the debug overlay, replacing whatever was there, if the terminal has room
+/// for it to the right of the board. It's one column away from the border, starting at the top of
+/// the terminal. Nothing is flushed.
+fn queue_debug_line(
+    game: &Game,
+    out: &mut impl Write,
+    index: usize,
+    line: &str,
+) -> crossterm::Result<()> {
+    // if the size can't be determined, assume the line fits
+    let (columns, rows) = terminal::size().unwrap_or((u16::MAX, u16::MAX));
+    let (needed_columns, _) =
+        needed_terminal_size(game.height, game.width, game.theme.border_width());
+    let column = needed_columns + 1;
+    let fits = (columns, rows) == (0, 0)
+        || (column + line.chars().count() as u32 <= u32::from(columns)
+            && index < usize::from(rows));
+    if !fits {
+        return Ok(());
+    }
+    // with an unknown terminal size, or a huge board, the line may be past what can be addressed
+    let (Ok(column), Ok(row)) = (u16::try_from(column), u16::try_from(index)) else {
+        return Ok(());
+    };
+    out.queue(cursor::MoveTo(column, row))?
+        .queue(Print(line))?
+        .queue(Clear(ClearType::UntilNewLine))?;
+    Ok(())
+}
+
+/// Queue every line of the debug overlay that's `shown` again, e.g. after the screen was cleared.
+/// Nothing is flushed.
+fn queue_debug_overlay(
+    game: &Game,
+    out: &mut impl Write,
+    shown: &Mutex<Vec<String>>,
+) -> crossterm::Result<()> {
+    for (index, line) in shown.lock().unwrap().iter().enumerate() {
+        queue_debug_line(game, out, index, line)?;
+    }
+    Ok(())
+}
+
+/// Queue the lines of the debug overlay that differ from what's `shown`, so that only the ones that
+/// changed are drawn again, to avoid flicker. `shown` is updated to match. Nothing is flushed.
+fn update_debug_overlay(
+    game: &Game,
+    out: &mut impl Write,
+    shown: &Mutex<Vec<String>>,
+) -> crossterm::Result<()> {
+    let mut shown = shown.lock().unwrap();
+    for (index, line) in debug_lines(game).into_iter().enumerate() {
+        if shown.get(index) != Some(&line) {
+            queue_debug_line(game, out, index, &line)?;
+            match shown.get_mut(index) {
+                Some(shown_line) => *shown_line = line,
+                None => shown.push(line),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Queue a box saying how the game ended, with the final scores, over the middle of the board.
+/// `restart` is whether to offer restarting. Nothing is flushed.
+fn queue_end_screen(game: &Game, out: &mut impl Write, restart: bool) -> crossterm::Result<()> {
+    let title = match (game.state, game.winner()) {
+        (GameState::WON, Some(winner)) => format!("PLAYER {} WINS!", winner + 1),
+        (GameState::WON, None) => "YOU WON!".to_string(),
+        _ => "GAME OVER".to_string(),
+    };
+    let mut texts = vec![title];
+    if let (GameState::DEAD, Some(cause), 1) = (game.state, game.death_cause(), game.player_count())
+    {
+        texts.push(cause.to_string());
+    }
+    texts.push(String::new());
+    if game.player_count() > 1 {
+        for player in 0..game.player_count() {
+            texts.push(format!(
+                "Player {}: {}",
+                player + 1,
+                game.player_score(player)
+            ));
+        }
+    } else {
+        texts.push(format!("Score: {}", game.score()));
+    }
+    texts.push(String::new());
+    if restart {
+        texts.push("Press R to restart".to_string());
+        texts.push("or any key to quit".to_string());
+    } else {
+        texts.push("Press any key".to_string());
+    }
+
+    let inner_width = texts
+        .iter()
+        .map(|text| text.chars().count())
+        .max()
+        .unwrap_or(0)
+        + 2;
+    let mut lines = vec![format!("┌{}┐", "─".repeat(inner_width))];
+    for text in &texts {
+        lines.push(format!("│{:^width$}│", text, width = inner_width));
+    }
+    lines.push(format!("└{}┘", "─".repeat(inner_width)));
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    queue_centered(game, out, &lines)
+}
+
+/// Hides the cursor and asks for focus events while alive. When dropped, even during a panic, the
+/// board is cleared and the cursor is shown again so that the terminal is left usable.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new() -> crossterm::Result<TerminalGuard> {
+        // terminals that don't report focus changes ignore this
+        stdout().queue(Hide)?.queue(EnableFocusChange)?.flush()?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        // there's no way to report an error from here, and nothing better to do about it anyway
+        let _ = stdout()
+            .queue(Clear(ClearType::All))
+            .and_then(|out| out.queue(cursor::MoveTo(0, 0)))
+            .and_then(|out| out.queue(Show))
+            .and_then(|out| out.queue(DisableFocusChange))
+            .and_then(|out| out.flush());
+    }
+}
+
+/// A custom way of drawing the game, for `InteractiveGame::with_renderer`.
+type Renderer = Arc<Mutex<Box<dyn FnMut(&Game) + Send>>>;
+
+pub struct InteractiveGame {
+    game_mut: Arc<Mutex<Game>>,
+    tick_wait: time::Duration,
+    poll_interval: time::Duration,
+    paused: Arc<AtomicBool>,
+    too_small: Arc<AtomicBool>, // whether the terminal is too small for the board
+    events: Option<Sender<GameEvent>>,
+    #[cfg(feature = "spectate")]
+    spectators: Option<TcpListener>,
+    speed_curve: Option<fn(usize) -> time::Duration>,
+    countdown: Option<time::Duration>,
+    timer: bool,
+    hud: bool,
+    status: Arc<Mutex<String>>, // the line below the board, as last drawn
+    debug_overlay: Option<Arc<Mutex<Vec<String>>>>, // the lines of the overlay, as last drawn
+    controller: Option<fn(&Game) -> Input>,
+    script: Vec<(u64, Input)>,
+    renderer: Option<Renderer>,
+    key_bindings: KeyBindings,
+    counting_down: Arc<AtomicBool>,
+    restart: bool,
+    ended: Arc<AtomicBool>,    // whether the end screen is showing
+    finished: Arc<AtomicBool>, // whether the end screen has been dismissed
+}
+impl InteractiveGame {
+    pub fn new(game: Game, tick_wait: time::Duration) -> InteractiveGame {
+        InteractiveGame {
+            game_mut: Arc::new(Mutex::new(game)),
+            tick_wait,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            paused: Arc::new(AtomicBool::new(false)),
+            too_small: Arc::new(AtomicBool::new(false)),
+            events: None,
+            #[cfg(feature = "spectate")]
+            spectators: None,
+            speed_curve: None,
+            countdown: None,
+            timer: false,
+            hud: false,
+            status: Arc::new(Mutex::new(String::new())),
+            debug_overlay: None,
+            controller: None,
+            script: Vec::new(),
+            renderer: None,
+            key_bindings: KeyBindings::default(),
+            counting_down: Arc::new(AtomicBool::new(false)),
+            restart: false,
+            ended: Arc::new(AtomicBool::new(false)),
+            finished: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Check for input every `poll_interval` instead of the default of 16ms. Inputs are queued as
+    /// soon as they're read, however slow the game is.
+    pub fn with_poll_interval(mut self, poll_interval: time::Duration) -> InteractiveGame {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Wait `speed_curve(score)` between ticks instead of the fixed tick wait, e.g. to speed the
+    /// game up as the snake grows. It's recomputed after every tick.
+    pub fn with_speed_curve(mut self, speed_curve: fn(usize) -> time::Duration) -> InteractiveGame {
+        self.speed_curve = Some(speed_curve);
+        self
+    }
+
+    /// Count down "3", "2", "1", "Go!" over the board before the snake starts moving, showing each
+    /// for `step`. Movement keys are ignored until it's done.
+    pub fn with_countdown(mut self, step: time::Duration) -> InteractiveGame {
+        self.countdown = Some(step);
+        self
+    }
+
+    /// Show how long the game has been played for on the line below the board, if the terminal has
+    /// room for it. The timer doesn't run while the game is paused or counting down, and stops when
+    /// the game ends.
+    pub fn with_timer(mut self) -> InteractiveGame {
+        self.timer = true;
+        self
+    }
+
+    /// Show the first player's score and length on the line below the board, if the terminal has
+    /// room for it. If there's a timer as well, it's shown after them.
+    pub fn with_hud(mut self) -> InteractiveGame {
+        self.hud = true;
+        self
+    }
+
+    /// Show where the first player's snake's head is, which way it's heading, the tick count and
+    /// the snake's length in the top right, next to the board, e.g. while working on a level. It's
+    /// updated every tick, but only the lines that changed are drawn again. It's left out if the
+    /// terminal isn't wide enough for it.
+    pub fn with_debug_overlay(mut self) -> InteractiveGame {
+        self.debug_overlay = Some(Arc::new(Mutex::new(Vec::new())));
+        self
+    }
+
+    /// Offer to restart the game from the end screen. A restarted game starts over as the game was
+    /// when it was created, as with `Game::reset`, but with a new random seed. The score that's
+    /// returned is the one of the last game.
+    pub fn with_restart(mut self) -> InteractiveGame {
+        self.restart = true;
+        self
+    }
+
+    /// Draw the game with `renderer` instead of in the terminal, e.g. with overlays of your own or
+    /// to log it. It's called with the game at the start of every round and after every tick.
+    /// Nothing else is drawn then: not the board, the countdown, the line below the board or the
+    /// end screen, so it's up to `renderer` to show how the game ended. Keys are still read from
+    /// the terminal.
+    pub fn with_renderer(
+        mut self,
+        renderer: impl FnMut(&Game) + Send + 'static,
+    ) -> InteractiveGame {
+        self.renderer = Some(Arc::new(Mutex::new(Box::new(renderer))));
+        self
+    }
+
+    /// Steer with `key_bindings` instead of `wasd` and the arrow keys.
+    pub fn with_key_bindings(mut self, key_bindings: KeyBindings) -> InteractiveGame {
+        self.key_bindings = key_bindings;
+        self
+    }
+
+    /// Let `controller` steer the first player's snake instead of the keyboard, e.g.
+    /// `ai::next_move`. It's asked for a move before every tick.
+    pub fn with_controller(mut self, controller: fn(&Game) -> Input) -> InteractiveGame {
+        self.controller = Some(controller);
+        self
+    }
+
+    /// Queue each of the first player's `inputs` once the game has done the number of ticks paired
+    /// with it, like `Game::replay` does, alongside whatever the keyboard or the controller queues.
+    /// The ticks must be in order. Every restarted game plays the script from the start again, so
+    /// with `GameBuilder::seed` this gives the same game every time, e.g. for a demo.
+    pub fn with_script(mut self, inputs: Vec<(u64, Input)>) -> InteractiveGame {
+        self.script = inputs;
+        self
+    }
+
+    /// Send a `GameEvent` on `events` for everything that happens during play.
+    pub fn with_events(mut self, events: Sender<GameEvent>) -> InteractiveGame {
+        self.events = Some(events);
+        self
+    }
+
+    /// Let spectators watch the game over the network by connecting to `listener`, e.g. with
+    /// `spectate::spectate`. A spectator that can't keep up is disconnected rather than slowing
+    /// down the game. This needs the `spectate` feature.
+    #[cfg(feature = "spectate")]
+    pub fn with_spectators(mut self, listener: TcpListener) -> InteractiveGame {
+        self.spectators = Some(listener);
+        self
+    }
+
+    /// Record the game as it's played, to be retrieved with `recording` afterwards. Fails if the
+    /// game can't be replayed, as with `Game::start_recording`.
+    pub fn with_recording(self) -> Result<InteractiveGame, GameError> {
+        self.game_mut.lock().unwrap().start_recording()?;
+        Ok(self)
+    }
+
+    /// The replay recorded so far, if `with_recording` was used.
+    pub fn recording(&self) -> Option<Replay> {
+        self.game_mut.lock().unwrap().recording().cloned()
+    }
+
+    /// Play a new game with the default settings on a board of the given size. Returns how it ended,
+    /// or an error if the board doesn't fit in the terminal.
+    pub fn play(
+        height: u16,
+        width: u16,
+        tick_wait: time::Duration,
+    ) -> Result<GameSummary, GameError> {
+        check_fits_terminal(height, width, &Theme::default())?;
+        Ok(InteractiveGame::new(Game::create(height, width)?, tick_wait).run())
+    }
+
+    /// Like `play`, but the food is seeded with `todays_seed`, so that everyone who plays on the
+    /// same day, on a board of the same size, gets the same game given the same moves.
+    pub fn play_daily(
+        height: u16,
+        width: u16,
+        tick_wait: time::Duration,
+    ) -> Result<GameSummary, GameError> {
+        check_fits_terminal(height, width, &Theme::default())?;
+        let game = Game::create_seeded(height, width, todays_seed())?;
+        Ok(InteractiveGame::new(game, tick_wait).run())
+    }
+
+    /// Play the game in the terminal until it ends, and return how it ended. Nothing is printed
+    /// once the board is cleared, so presenting the outcome is up to the caller. Unless the game
+    /// was quit, an end screen is shown until a key is pressed. The terminal should already be in
+    /// raw mode.
+    ///
+    /// Unless `supports_truecolor` says the terminal can show them, RGB colors in the theme are
+    /// drawn as the closest of the 256 colors.
+    ///
+    /// Ctrl-C quits the game like `q` does, and the terminal is restored the same way. That only
+    /// works while this is running: an embedding program that drives `Game` itself, or leaves raw
+    /// mode enabled outside of this, should install its own signal handler.
+    pub fn run(&self) -> GameSummary {
+        let terminal_guard = TerminalGuard::new().unwrap();
+        if !supports_truecolor() {
+            let mut game = self.game_mut.lock().unwrap();
+            game.theme = game.theme.without_truecolor();
+        }
+        let tick_wait = self.tick_wait;
+        let poll_interval = self.poll_interval;
+        let restart = self.restart;
+
+        let ticker_mut = Arc::clone(&self.game_mut);
+        let ticker_paused = Arc::clone(&self.paused);
+        let ticker_too_small = Arc::clone(&self.too_small);
+        let ticker_ended = Arc::clone(&self.ended);
+        let ticker_finished = Arc::clone(&self.finished);
+        let ticker_events = self.events.clone();
+        #[cfg(feature = "spectate")]
+        let spectators = self
+            .spectators
+            .as_ref()
+            .map(|_| Arc::new(Spectators::default()));
+        #[cfg(feature = "spectate")]
+        let ticker_spectators = spectators.clone();
+        #[cfg(feature = "spectate")]
+        let stop_accepting = Arc::new(AtomicBool::new(false));
+        #[cfg(feature = "spectate")]
+        let acceptor = self
+            .spectators
+            .as_ref()
+            .zip(spectators)
+            .map(|(listener, spectators)| {
+                spectate::spawn_acceptor(
+                    listener.try_clone().unwrap(),
+                    Arc::clone(&self.game_mut),
+                    spectators,
+                    Arc::clone(&stop_accepting),
+                    poll_interval,
+                )
+                .unwrap()
+            });
+        let speed_curve = self.speed_curve;
+        let countdown = self.countdown;
+        let timer = self.timer;
+        let hud = self.hud;
+        let ticker_status = Arc::clone(&self.status);
+        let ticker_debug_overlay = self.debug_overlay.clone();
+        let controller = self.controller;
+        let script = self.script.clone();
+        let renderer = self.renderer.clone();
+        let has_renderer = renderer.is_some();
+        let ticker_counting_down = Arc::clone(&self.counting_down);
+        ticker_counting_down.store(countdown.is_some(), Ordering::SeqCst);
+        let ticker = thread::spawn(move || {
+            // - print the board
+            // - wait
+            // - tick
+            // and once the game is over, show the end screen until the input handler either
+            // restarts the game or is done with it
+            // buffered so that each frame reaches the terminal in one write when it's flushed
+            let mut stdout = BufWriter::new(stdout());
+            loop {
+                {
+                    let game = ticker_mut.lock().unwrap();
+                    if let Some(renderer) = &renderer {
+                        (renderer.lock().unwrap())(&game);
+                    } else {
+                        // the board is redrawn from scratch, without the status line or the overlay
+                        ticker_status.lock().unwrap().clear();
+                        if let Some(shown) = &ticker_debug_overlay {
+                            shown.lock().unwrap().clear();
+                        }
+                        // if the size can't be determined, assume the board fits
+                        let terminal_size = terminal::size().unwrap_or((u16::MAX, u16::MAX));
+                        let fits = redraw(&game, &mut stdout, terminal_size).unwrap();
+                        ticker_too_small.store(!fits, Ordering::SeqCst);
+                    }
+                    #[cfg(feature = "spectate")]
+                    if let Some(spectators) = &ticker_spectators {
+                        spectators.send(spectate::board_frame(&game));
+                    }
+                }
+                stdout.flush().unwrap();
+
+                if let Some(step) = countdown {
+                    for label in ["3", "2", "1", "Go!"] {
+                        {
+                            let game = ticker_mut.lock().unwrap();
+                            if game.state != GameState::RUNNING {
+                                break;
+                            }
+                            if !has_renderer && !ticker_too_small.load(Ordering::SeqCst) {
+                                // redraw to erase the previous label
+                                game.redraw_all(&mut stdout).unwrap();
+                                queue_centered(&game, &mut stdout, &[label]).unwrap();
+                            }
+                            stdout.flush().unwrap();
+                        }
+                        thread::sleep(step);
+                    }
+                    let game = ticker_mut.lock().unwrap();
+                    if !has_renderer && !ticker_too_small.load(Ordering::SeqCst) {
+                        game.redraw_all(&mut stdout).unwrap();
+                        stdout.flush().unwrap();
+                    }
+                    ticker_counting_down.store(false, Ordering::SeqCst);
+                }
+
+                let mut term_updates: Vec<TermUpdate> = Vec::new();
+                let mut score = 0;
+                let mut elapsed = time::Duration::ZERO;
+                let mut scripted = script.iter().peekable();
+                // ticks are due at fixed intervals from here, however long ticking and drawing take
+                let mut last_tick = Instant::now();
+                let mut next_tick = last_tick;
+                loop {
+                    {
+                        // hold the lock so that a redraw by the input handler can't interleave
+                        // with this
+                        let game = ticker_mut.lock().unwrap();
+                        // don't draw over the message asking for a bigger terminal
+                        if !has_renderer && !ticker_too_small.load(Ordering::SeqCst) {
+                            for term_update in &term_updates {
+                                term_update.queue(&mut stdout, &game).unwrap();
+                            }
+                            if game.theme.gradient.is_some() && !term_updates.is_empty() {
+                                game.redraw_snakes(&mut stdout).unwrap();
+                            }
+                            let status = status_line(&game, hud, timer.then_some(elapsed));
+                            update_status(&game, &mut stdout, &ticker_status, status).unwrap();
+                            if let Some(shown) = &ticker_debug_overlay {
+                                update_debug_overlay(&game, &mut stdout, shown).unwrap();
+                            }
+                        }
+                        stdout.flush().unwrap();
+                    }
+                    next_tick += match speed_curve {
+                        Some(speed_curve) => speed_curve(score),
+                        None => tick_wait,
+                    };
+                    match next_tick.checked_duration_since(Instant::now()) {
+                        Some(remaining) => thread::sleep(remaining),
+                        // behind, so tick right away, and don't rush the ticks after this one to
+                        // catch up
+                        None => next_tick = Instant::now(),
+                    }
+                    let now = Instant::now();
+                    let interval = now - last_tick;
+                    last_tick = now;
+                    {
+                        let mut game = ticker_mut.lock().unwrap();
+
+                        // the game may have been quit by the input handler while we were sleeping
+                        let paused = ticker_paused.load(Ordering::SeqCst)
+                            || ticker_too_small.load(Ordering::SeqCst);
+                        if game.state == GameState::RUNNING && !paused {
+                            elapsed += interval;
+                            if let Some(controller) = controller {
+                                let input = controller(&game);
+                                game.queue_input(input);
+                            }
+                            while let Some((_, input)) =
+                                scripted.next_if(|(tick, _)| *tick <= game.tick_count())
+                            {
+                                game.queue_input(*input);
+                            }
+                            let prev_scores: Vec<usize> =
+                                game.snakes.iter().map(|snake| snake.food_eaten).collect();
+                            game.tick_into(&mut term_updates);
+                            if let Some(renderer) = &renderer {
+                                (renderer.lock().unwrap())(&game);
+                            }
+                            if let Some(events) = &ticker_events {
+                                send_tick_events(events, &game, &prev_scores, interval);
+                            }
+                            #[cfg(feature = "spectate")]
+                            if let Some(spectators) = &ticker_spectators {
+                                spectators.send(spectate::updates_frame(game.state, &term_updates));
+                            }
+                            score = game.score();
+                        } else {
+                            // leave the board frozen
+                            term_updates.clear();
+                        }
+
+                        if game.state != GameState::RUNNING {
+                            break;
+                        }
+                    }
+                }
+
+                {
+                    let game = ticker_mut.lock().unwrap();
+                    #[cfg(feature = "spectate")]
+                    if let Some(spectators) = &ticker_spectators {
+                        // the game may have been quit without a tick
+                        spectators.send(spectate::updates_frame(game.state, &[]));
+                    }
+                    if game.state == GameState::QUIT {
+                        break;
+                    }
+                    if !has_renderer {
+                        if !ticker_too_small.load(Ordering::SeqCst) {
+                            let status = status_line(&game, hud, timer.then_some(elapsed));
+                            update_status(&game, &mut stdout, &ticker_status, status).unwrap();
+                            if let Some(shown) = &ticker_debug_overlay {
+                                update_debug_overlay(&game, &mut stdout, shown).unwrap();
+                            }
+                        }
+                        queue_end_screen(&game, &mut stdout, restart).unwrap();
+                        stdout.flush().unwrap();
+                    }
+                    ticker_ended.store(true, Ordering::SeqCst);
+                }
+                // wait for the input handler to restart the game or be done with it
+                while ticker_ended.load(Ordering::SeqCst) {
+                    if ticker_finished.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    thread::sleep(poll_interval);
+                }
+            }
+        });
+
+        let input_handler_mut = Arc::clone(&self.game_mut);
+        let input_handler_paused = Arc::clone(&self.paused);
+        let input_handler_too_small = Arc::clone(&self.too_small);
+        let input_handler_counting_down = Arc::clone(&self.counting_down);
+        let input_handler_ended = Arc::clone(&self.ended);
+        let input_handler_finished = Arc::clone(&self.finished);
+        let has_controller = self.controller.is_some();
+        let key_bindings = self.key_bindings.clone();
+        let input_handler_status = Arc::clone(&self.status);
+        let input_handler_debug_overlay = self.debug_overlay.clone();
+        // with two players, the first one gets the primary keys and the second one the secondary
+        // keys
+        let secondary_player = if self.game_mut.lock().unwrap().player_count() > 1 {
+            1
+        } else {
+            0
+        };
+        // whether the game was paused because the terminal lost focus, so that it's only resumed
+        // on regaining it if the player didn't pause it themselves
+        let mut paused_by_focus = false;
+        let input_handler = thread::spawn(move || loop {
+            // checked on every iteration, so that a steady stream of events can't keep this going
+            // after the game is quit
+            if input_handler_mut.lock().unwrap().state == GameState::QUIT {
+                break;
+            }
+            if !poll(poll_interval).unwrap() {
+                continue;
+            }
+            let event = read().unwrap();
+            if let Event::Key(KeyEvent {
+                kind: KeyEventKind::Release,
+                ..
+            }) = event
+            {
+                // only presses count, but some platforms report releases as well
+                continue;
+            }
+            if input_handler_ended.load(Ordering::SeqCst) {
+                // the end screen is showing, so what's left is to restart or be done
+                match (event, restart) {
+                    (
+                        Event::Key(KeyEvent {
+                            code: Char('r') | Char('R'),
+                            ..
+                        }),
+                        true,
+                    ) => {
+                        // start over as the game was created, but with different food
+                        let mut game = input_handler_mut.lock().unwrap();
+                        game.reset();
+                        game.reseed();
+                        drop(game);
+                        input_handler_paused.store(false, Ordering::SeqCst);
+                        paused_by_focus = false;
+                        input_handler_counting_down.store(countdown.is_some(), Ordering::SeqCst);
+                        input_handler_ended.store(false, Ordering::SeqCst);
+                    }
+                    (Event::Key(_), _) => {
+                        input_handler_finished.store(true, Ordering::SeqCst);
+                        break;
+                    }
+                    (Event::Resize(columns, rows), _) if !has_renderer => {
+                        let game = input_handler_mut.lock().unwrap();
+                        let mut stdout = BufWriter::new(stdout());
+                        if redraw(&game, &mut stdout, (columns, rows)).unwrap() {
+                            let status = input_handler_status.lock().unwrap();
+                            queue_status(&game, &mut stdout, &status).unwrap();
+                            if let Some(shown) = &input_handler_debug_overlay {
+                                queue_debug_overlay(&game, &mut stdout, shown).unwrap();
+                            }
+                            queue_end_screen(&game, &mut stdout, restart).unwrap();
+                        }
+                        stdout.flush().unwrap();
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            let paused = input_handler_paused.load(Ordering::SeqCst);
+            let input = match event {
+                Event::Key(KeyEvent {
+                    modifiers, code, ..
+                }) => match code {
+                    // raw mode turns ctrl-c into a key press rather than SIGINT, so quit on it
+                    // here or there'd be no way to interrupt the game
+                    Char('c') | Char('C') if modifiers.contains(KeyModifiers::CONTROL) => {
+                        let mut game = input_handler_mut.lock().unwrap();
+                        if game.state == GameState::RUNNING {
+                            game.state = GameState::QUIT;
+                        }
+                        None
+                    }
+                    Char(' ') => {
+                        input_handler_paused.store(!paused, Ordering::SeqCst);
+                        paused_by_focus = false;
+                        None
+                    }
+                    Esc | Char('q') | Char('Q') => {
+                        let mut game = input_handler_mut.lock().unwrap();
+                        if game.state == GameState::RUNNING {
+                            game.state = GameState::QUIT;
+                        }
+                        None
+                    }
+                    // while paused, only unpausing and quitting are allowed
+                    _ if paused => None,
+                    _ if input_handler_counting_down.load(Ordering::SeqCst) => None,
+                    code => key_bindings
+                        .primary_input(code)
+                        .map(|input| (0, input))
+                        .or_else(|| {
+                            key_bindings
+                                .secondary_input(code)
+                                .map(|input| (secondary_player, input))
+                        }),
+                },
+                Event::Resize(columns, rows) if !has_renderer => {
+                    // the terminal may have moved or dropped what was drawn, so start over
+                    let game = input_handler_mut.lock().unwrap();
+                    let mut stdout = BufWriter::new(stdout());
+                    let fits = redraw(&game, &mut stdout, (columns, rows)).unwrap();
+                    if fits {
+                        let status = input_handler_status.lock().unwrap();
+                        queue_status(&game, &mut stdout, &status).unwrap();
+                        if let Some(shown) = &input_handler_debug_overlay {
+                            queue_debug_overlay(&game, &mut stdout, shown).unwrap();
+                        }
+                    }
+                    stdout.flush().unwrap();
+                    input_handler_too_small.store(!fits, Ordering::SeqCst);
+                    None
+                }
+                // so that the snake doesn't die while the player is looking elsewhere
+                Event::FocusLost if !paused => {
+                    input_handler_paused.store(true, Ordering::SeqCst);
+                    paused_by_focus = true;
+                    None
+                }
+                Event::FocusGained if paused_by_focus => {
+                    input_handler_paused.store(false, Ordering::SeqCst);
+                    paused_by_focus = false;
+                    None
+                }
+                _ => None,
+            };
+            match input {
+                // the controller steers the first player
+                Some((0, _)) if has_controller => {}
+                Some((0, i)) => input_handler_mut.lock().unwrap().queue_input(i),
+                Some((player, i)) => input_handler_mut
+                    .lock()
+                    .unwrap()
+                    .queue_player_input(player, i),
+                None => {}
+            }
+        });
+
+        ticker.join().unwrap();
+        input_handler.join().unwrap();
+        #[cfg(feature = "spectate")]
+        {
+            stop_accepting.store(true, Ordering::SeqCst);
+            if let Some(acceptor) = acceptor {
+                acceptor.join().unwrap();
+            }
+        }
+        drop(terminal_guard);
+
+        let game = self.game_mut.lock().unwrap();
+        GameSummary {
+            state: game.state,
+            score: game.score(),
+            ticks: game.tick_count(),
+        }
+    }
+}
@@ -0,0 +1,525 @@
+//! How the board is drawn and which keys steer the snakes in the terminal. This needs the
+//! `terminal` feature, like everything that uses crossterm.
+
+use crate::{
+    Coord, Input, Tile, AIR_STR, FOOD_STR, PORTAL_STR, SHRINK_FOOD_STR, SNAKE_STR, TRAIL_STR,
+    WALL_STR,
+};
+use crossterm::{
+    event::KeyCode::{self, Char, Down, Left, Right, Up},
+    style::{Color, StyledContent, Stylize},
+    tty::IsTty,
+};
+use once_cell::sync::Lazy;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::stdout;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static DEFAULT_THEME: Lazy<Theme> = Lazy::new(Theme::default);
+
+static STDOUT_IS_TTY: Lazy<bool> = Lazy::new(|| stdout().is_tty());
+
+/// The `ColorChoice` set with `set_color_choice`, as its index.
+static COLOR_CHOICE: AtomicU8 = AtomicU8::new(ColorChoice::Auto as u8);
+
+/// Whether boards printed through `Display` are styled with colors.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ColorChoice {
+    /// Only when stdout is a terminal, so that output redirected to a file or a pipe is plain text.
+    Auto,
+    Always,
+    Never,
+}
+
+/// Choose whether `Tile`, `Game` and the like are styled with colors when displayed. The default is
+/// `ColorChoice::Auto`. Drawing an `InteractiveGame`, and explicitly themed output such as
+/// `Game::to_board_string_themed`, aren't affected.
+pub fn set_color_choice(choice: ColorChoice) {
+    COLOR_CHOICE.store(choice as u8, Ordering::SeqCst);
+}
+
+/// Whether `Display` output is styled with colors, per `set_color_choice`.
+pub(crate) fn colors_enabled() -> bool {
+    match COLOR_CHOICE.load(Ordering::SeqCst) {
+        choice if choice == ColorChoice::Always as u8 => true,
+        choice if choice == ColorChoice::Never as u8 => false,
+        _ => *STDOUT_IS_TTY,
+    }
+}
+
+impl Tile {
+    /// Style this tile with the glyph and color that `theme` gives it.
+    pub fn render_with<'a>(&self, theme: &'a Theme) -> StyledContent<&'a str> {
+        let style = match self {
+            Tile::HEAD => &theme.head,
+            Tile::SNAKE => &theme.snake,
+            Tile::RIVALHEAD => &theme.rival_head,
+            Tile::RIVAL => &theme.rival,
+            Tile::FOOD => &theme.food,
+            Tile::SHRINK => &theme.shrink,
+            Tile::TRAIL => &theme.trail,
+            Tile::AIR => &theme.air,
+            Tile::WALL => &theme.wall,
+            Tile::PORTAL => &theme.portal,
+        };
+        style.render()
+    }
+}
+
+impl fmt::Display for Tile {
+    /// The tile as drawn by the default theme, without colors if `set_color_choice` says so.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if colors_enabled() {
+            write!(f, "{}", self.render_with(&DEFAULT_THEME))
+        } else {
+            f.write_str(self.render_with(&DEFAULT_THEME).content())
+        }
+    }
+}
+
+/// How a single kind of tile is drawn.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TileStyle {
+    pub glyph: String,
+    /// The foreground color, or `None` to keep the terminal's own. Any color works, including
+    /// 256-color `Color::AnsiValue` and `Color::Rgb`, but see `Theme::without_truecolor`.
+    pub color: Option<Color>,
+}
+
+impl TileStyle {
+    pub fn new(glyph: &str, color: Option<Color>) -> TileStyle {
+        TileStyle {
+            glyph: glyph.to_string(),
+            color,
+        }
+    }
+
+    pub(crate) fn render(&self) -> StyledContent<&str> {
+        styled(&self.glyph, self.color)
+    }
+}
+
+fn styled(glyph: &str, color: Option<Color>) -> StyledContent<&str> {
+    match color {
+        Some(color) => glyph.with(color),
+        None => glyph.stylize(),
+    }
+}
+
+/// Which way up the board is drawn. Either way, only the drawing changes, not the coordinates of
+/// the game.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Orientation {
+    /// With (0, 0) at the top left, and y growing downwards like the rows of the terminal.
+    TopDown,
+    /// With (0, 0) at the bottom left, and y growing upwards like in math. `Input::UP` still moves
+    /// towards y = 0, which is down on the screen, so key bindings may want swapping to match.
+    BottomUp,
+}
+
+/// How the border around the board is drawn.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BorderStyle {
+    /// With the wall tile, like the walls inside of the board.
+    Wall,
+    /// With a glyph and color of its own.
+    Custom(TileStyle),
+    /// With thin box-drawing lines, in the color of the walls.
+    Thin,
+    /// Not at all, and without taking any room, so that the board starts at the top left corner of
+    /// the terminal. This suits `WallMode::Wrap`, where the edges of the board aren't walls.
+    Hidden,
+}
+
+/// Which keys steer the snakes in interactive play. Letter keys match regardless of case.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KeyBindings {
+    /// The keys that steer the first player.
+    pub primary: Vec<(KeyCode, Input)>,
+    /// The keys that steer the second player, or the first one as well in a single-player game.
+    pub secondary: Vec<(KeyCode, Input)>,
+}
+
+impl KeyBindings {
+    /// `hjkl` like in vim, and the arrow keys.
+    pub fn vim() -> KeyBindings {
+        KeyBindings {
+            primary: vec![
+                (Char('k'), Input::UP),
+                (Char('h'), Input::LEFT),
+                (Char('j'), Input::DOWN),
+                (Char('l'), Input::RIGHT),
+            ],
+            ..KeyBindings::default()
+        }
+    }
+
+    /// The input that `code` is bound to for the first player.
+    pub fn primary_input(&self, code: KeyCode) -> Option<Input> {
+        find_binding(&self.primary, code)
+    }
+
+    /// The input that `code` is bound to for the second player.
+    pub fn secondary_input(&self, code: KeyCode) -> Option<Input> {
+        find_binding(&self.secondary, code)
+    }
+
+    /// The input that `code` is bound to for either player, as in single-player play.
+    pub fn input(&self, code: KeyCode) -> Option<Input> {
+        self.primary_input(code)
+            .or_else(|| self.secondary_input(code))
+    }
+}
+
+/// The key code of a single character, or of an arrow key named like `up`, in either case.
+pub(crate) fn parse_key_code(s: &str) -> Option<KeyCode> {
+    let mut chars = s.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(Char(c));
+    }
+    match s.to_ascii_lowercase().as_str() {
+        "up" => Some(Up),
+        "left" => Some(Left),
+        "down" => Some(Down),
+        "right" => Some(Right),
+        _ => None,
+    }
+}
+
+impl Default for KeyBindings {
+    /// `wasd`, and the arrow keys.
+    fn default() -> KeyBindings {
+        KeyBindings {
+            primary: vec![
+                (Char('w'), Input::UP),
+                (Char('a'), Input::LEFT),
+                (Char('s'), Input::DOWN),
+                (Char('d'), Input::RIGHT),
+            ],
+            secondary: vec![
+                (Up, Input::UP),
+                (Left, Input::LEFT),
+                (Down, Input::DOWN),
+                (Right, Input::RIGHT),
+            ],
+        }
+    }
+}
+
+fn find_binding(bindings: &[(KeyCode, Input)], code: KeyCode) -> Option<Input> {
+    let code = match code {
+        Char(c) => Char(c.to_ascii_lowercase()),
+        code => code,
+    };
+    bindings
+        .iter()
+        .find(|(bound, _)| match bound {
+            Char(c) => Char(c.to_ascii_lowercase()) == code,
+            bound => *bound == code,
+        })
+        .map(|(_, input)| *input)
+}
+
+/// The glyphs and colors the board is drawn with. Each glyph should be a single column wide.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Theme {
+    pub head: TileStyle,
+    pub snake: TileStyle,
+    pub rival_head: TileStyle,
+    pub rival: TileStyle,
+    pub food: TileStyle,
+    pub shrink: TileStyle,
+    pub trail: TileStyle,
+    pub air: TileStyle,
+    pub wall: TileStyle,
+    pub portal: TileStyle,
+    pub border: BorderStyle,
+    pub orientation: Orientation,
+    /// The color the snakes' bodies fade to towards the tail, from their own color next to the
+    /// head, or `None` to draw them in a single color. The colors are blended as RGB, with named and
+    /// 256 colors taken at their usual xterm values, and the result is one of the 256 colors unless
+    /// either end is `Color::Rgb`. The default is `None`.
+    pub gradient: Option<Color>,
+}
+
+impl Theme {
+    /// A theme that only uses 7-bit ASCII glyphs, for terminals and logs without Unicode support.
+    pub fn ascii() -> Theme {
+        Theme {
+            head: TileStyle::new("@", Some(Color::Green)),
+            snake: TileStyle::new("o", Some(Color::Green)),
+            rival_head: TileStyle::new("X", Some(Color::Blue)),
+            rival: TileStyle::new("x", Some(Color::Blue)),
+            food: TileStyle::new(".", Some(Color::Red)),
+            shrink: TileStyle::new(SHRINK_FOOD_STR, Some(Color::Magenta)),
+            trail: TileStyle::new(",", Some(Color::DarkGreen)),
+            air: TileStyle::new(" ", None),
+            wall: TileStyle::new("#", Some(Color::White)),
+            portal: TileStyle::new("O", Some(Color::DarkCyan)),
+            border: BorderStyle::Wall,
+            orientation: Orientation::TopDown,
+            gradient: None,
+        }
+    }
+
+    /// This theme with every `Color::Rgb` replaced by the closest of the 256 colors, for terminals
+    /// that don't support truecolor. `InteractiveGame` does this by itself when
+    /// `supports_truecolor` says so.
+    pub fn without_truecolor(&self) -> Theme {
+        self.map_colors(|color| color.map(ansi_256_fallback))
+    }
+
+    /// This theme with no colors at all, which draws plain text.
+    pub fn without_colors(&self) -> Theme {
+        self.map_colors(|_| None)
+    }
+
+    fn map_colors(&self, map: impl Fn(Option<Color>) -> Option<Color>) -> Theme {
+        let fallback = |style: &TileStyle| TileStyle {
+            glyph: style.glyph.clone(),
+            color: map(style.color),
+        };
+        Theme {
+            head: fallback(&self.head),
+            snake: fallback(&self.snake),
+            rival_head: fallback(&self.rival_head),
+            rival: fallback(&self.rival),
+            food: fallback(&self.food),
+            shrink: fallback(&self.shrink),
+            trail: fallback(&self.trail),
+            air: fallback(&self.air),
+            wall: fallback(&self.wall),
+            portal: fallback(&self.portal),
+            border: match &self.border {
+                BorderStyle::Custom(style) => BorderStyle::Custom(fallback(style)),
+                border => border.clone(),
+            },
+            orientation: self.orientation,
+            gradient: map(self.gradient),
+        }
+    }
+
+    /// Where board coordinate `coord` of a board with the given height is drawn, as a board
+    /// coordinate of the unflipped board. Going the other way is the same.
+    pub(crate) fn oriented(&self, coord: &Coord, height: isize) -> Coord {
+        match self.orientation {
+            Orientation::TopDown => coord.clone(),
+            Orientation::BottomUp => Coord {
+                x: coord.x,
+                y: height - 1 - coord.y,
+            },
+        }
+    }
+
+    /// Segment `index` of a snake of `len` segments, counting the head as 0, in `player`'s color
+    /// faded towards the gradient by how far along the body it is. The head isn't drawn like this.
+    pub(crate) fn render_segment(
+        &self,
+        player: usize,
+        index: usize,
+        len: usize,
+    ) -> StyledContent<&str> {
+        let style = if player == 0 {
+            &self.snake
+        } else {
+            &self.rival
+        };
+        let color = match (style.color, self.gradient) {
+            // the segment behind the head is the snake's own color, and the tail the gradient's
+            (Some(from), Some(to)) if len > 2 => {
+                let progress = (index.max(1) - 1) as f64 / (len - 2) as f64;
+                Some(blend(from, to, progress.min(1.0)))
+            }
+            (color, _) => color,
+        };
+        styled(&style.glyph, color)
+    }
+
+    /// How many cells the border takes on each side of the board: 0 when it's hidden, and 1
+    /// otherwise.
+    pub(crate) fn border_width(&self) -> isize {
+        match self.border {
+            BorderStyle::Hidden => 0,
+            _ => 1,
+        }
+    }
+
+    /// The border at `coord`, which must be just outside of a board with the given coordinate
+    /// limits.
+    pub(crate) fn render_border(
+        &self,
+        coord: &Coord,
+        (width, height): (isize, isize),
+    ) -> StyledContent<&str> {
+        match &self.border {
+            BorderStyle::Wall | BorderStyle::Hidden => self.wall.render(),
+            BorderStyle::Custom(style) => style.render(),
+            BorderStyle::Thin => {
+                let (left, right) = (coord.x == -1, coord.x == width);
+                let (top, bottom) = (coord.y == -1, coord.y == height);
+                let glyph = match (left, right, top, bottom) {
+                    (true, _, true, _) => "┌",
+                    (_, true, true, _) => "┐",
+                    (true, _, _, true) => "└",
+                    (_, true, _, true) => "┘",
+                    (_, _, true, _) | (_, _, _, true) => "─",
+                    _ => "│",
+                };
+                styled(glyph, self.wall.color)
+            }
+        }
+    }
+}
+
+/// Whether the terminal says it supports 24-bit colors, through the `COLORTERM` environment
+/// variable.
+pub fn supports_truecolor() -> bool {
+    std::env::var("COLORTERM").is_ok_and(|value| value == "truecolor" || value == "24bit")
+}
+
+/// The levels of each component in the 6x6x6 color cube of the 256 colors, which starts at 16.
+const ANSI_CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The red, green and blue of `color` at the usual xterm values, or `None` for `Color::Reset`.
+fn rgb(color: Color) -> Option<(u8, u8, u8)> {
+    const BASIC: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (128, 0, 0),
+        (0, 128, 0),
+        (128, 128, 0),
+        (0, 0, 128),
+        (128, 0, 128),
+        (0, 128, 128),
+        (192, 192, 192),
+        (128, 128, 128),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (0, 0, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    let index = match color {
+        Color::Reset => return None,
+        Color::Rgb { r, g, b } => return Some((r, g, b)),
+        Color::AnsiValue(value) => value,
+        Color::Black => 0,
+        Color::DarkRed => 1,
+        Color::DarkGreen => 2,
+        Color::DarkYellow => 3,
+        Color::DarkBlue => 4,
+        Color::DarkMagenta => 5,
+        Color::DarkCyan => 6,
+        Color::Grey => 7,
+        Color::DarkGrey => 8,
+        Color::Red => 9,
+        Color::Green => 10,
+        Color::Yellow => 11,
+        Color::Blue => 12,
+        Color::Magenta => 13,
+        Color::Cyan => 14,
+        Color::White => 15,
+    };
+    let level = |value: u8| ANSI_CUBE_LEVELS[usize::from(value)];
+    Some(match index {
+        0..=15 => BASIC[usize::from(index)],
+        16..=231 => {
+            let cube = index - 16;
+            (level(cube / 36), level(cube / 6 % 6), level(cube % 6))
+        }
+        _ => {
+            let gray = 8 + 10 * (index - 232);
+            (gray, gray, gray)
+        }
+    })
+}
+
+/// The color `progress` of the way from `from` to `to`, as an RGB color if either of them is one
+/// and otherwise as the closest of the 256 colors. If either has no RGB value, it's `from`.
+fn blend(from: Color, to: Color, progress: f64) -> Color {
+    let (Some(start), Some(end)) = (rgb(from), rgb(to)) else {
+        return from;
+    };
+    let mix =
+        |a: u8, b: u8| (f64::from(a) + (f64::from(b) - f64::from(a)) * progress).round() as u8;
+    let blended = Color::Rgb {
+        r: mix(start.0, end.0),
+        g: mix(start.1, end.1),
+        b: mix(start.2, end.2),
+    };
+    match (from, to) {
+        (Color::Rgb { .. }, _) | (_, Color::Rgb { .. }) => blended,
+        _ => ansi_256_fallback(blended),
+    }
+}
+
+/// The closest of the 256 colors to `color` if it's an RGB color, or `color` itself otherwise.
+fn ansi_256_fallback(color: Color) -> Color {
+    let (r, g, b) = match color {
+        Color::Rgb { r, g, b } => (r, g, b),
+        color => return color,
+    };
+    // the color cube starts at 16, with ANSI_CUBE_LEVELS for each component
+    let nearest_level = |value: u8| {
+        (0..ANSI_CUBE_LEVELS.len())
+            .min_by_key(|&index| ANSI_CUBE_LEVELS[index].abs_diff(value))
+            .unwrap()
+    };
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (
+        16 + 36 * ri + 6 * gi + bi,
+        (
+            ANSI_CUBE_LEVELS[ri],
+            ANSI_CUBE_LEVELS[gi],
+            ANSI_CUBE_LEVELS[bi],
+        ),
+    );
+    // and the 24 grays from 232 go from 8 to 238 in steps of 10
+    let average = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+    let gray_index = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_level = 8 + 10 * gray_index;
+    let gray = (
+        232 + usize::from(gray_index),
+        (gray_level, gray_level, gray_level),
+    );
+    let squared_distance = |(lr, lg, lb): (u8, u8, u8)| {
+        [(r, lr), (g, lg), (b, lb)]
+            .iter()
+            .map(|&(a, b)| u32::from(a.abs_diff(b)).pow(2))
+            .sum::<u32>()
+    };
+    let (index, _) = [cube, gray]
+        .into_iter()
+        .min_by_key(|&(_, level)| squared_distance(level))
+        .unwrap();
+    Color::AnsiValue(index as u8)
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            head: TileStyle::new(SNAKE_STR, Some(Color::Yellow)),
+            snake: TileStyle::new(SNAKE_STR, Some(Color::Green)),
+            rival_head: TileStyle::new(SNAKE_STR, Some(Color::Cyan)),
+            rival: TileStyle::new(SNAKE_STR, Some(Color::Blue)),
+            food: TileStyle::new(FOOD_STR, Some(Color::Red)),
+            shrink: TileStyle::new(SHRINK_FOOD_STR, Some(Color::Magenta)),
+            trail: TileStyle::new(TRAIL_STR, Some(Color::DarkGreen)),
+            air: TileStyle::new(AIR_STR, None),
+            wall: TileStyle::new(WALL_STR, Some(Color::White)),
+            portal: TileStyle::new(PORTAL_STR, Some(Color::DarkCyan)),
+            border: BorderStyle::Wall,
+            orientation: Orientation::TopDown,
+            gradient: None,
+        }
+    }
+}